@@ -0,0 +1,18 @@
+// Operation sequences decoded from the fuzzer's input, replayed against the reference
+// model of `tests/model`. Run with `cargo fuzz run ops` from the repository root.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use treap::{MaxPolicy,MinPolicy};
+
+#[path = "../../tests/model/mod.rs"]
+mod model;
+
+fuzz_target!(|data: &[u8]| {
+    // the first byte picks the policy, so one corpus covers both
+    match data.split_first() {
+        Some((policy,rest)) if policy % 2 == 1 => model::replay::<MinPolicy>(&model::decode(rest)),
+        Some((_,rest)) => model::replay::<MaxPolicy>(&model::decode(rest)),
+        None => {},
+    }
+});
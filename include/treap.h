@@ -0,0 +1,64 @@
+/* C interface to the treap crate, built with its `ffi` feature; see src/ffi.rs. */
+#ifndef TREAP_H
+#define TREAP_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define TREAP_OK 0
+/* no such key, or no entry to pop */
+#define TREAP_ABSENT 1
+/* null pointer where one isn't allowed, or a corrupted treap */
+#define TREAP_ERROR (-1)
+
+/* Treap of byte-string keys, int64_t priorities (highest on top) and byte-string values.
+ * Keys order as bytes; the _i64 functions encode an integer key as 8 big-endian bytes
+ * with the sign bit flipped, so integer keys order as integers. */
+typedef struct treap treap_t;
+
+/* Buffer owned by the caller, freed with treap_bytes_free; ptr is NULL only if len is 0. */
+typedef struct {
+    uint8_t *ptr;
+    size_t len;
+} treap_bytes_t;
+
+typedef struct {
+    treap_bytes_t key;
+    int64_t priority;
+    treap_bytes_t value;
+} treap_entry_t;
+
+treap_t *treap_new(void);
+void treap_free(treap_t *treap);
+size_t treap_len(const treap_t *treap);
+
+/* Inserts or replaces the entry of key. Pointers may be NULL when their length is 0. */
+int treap_insert(treap_t *treap, const uint8_t *key, size_t key_len, int64_t priority, const uint8_t *value, size_t value_len);
+int treap_insert_i64(treap_t *treap, int64_t key, int64_t priority, const uint8_t *value, size_t value_len);
+
+/* Copy out the priority and value of key; either out-pointer may be NULL. */
+int treap_get(const treap_t *treap, const uint8_t *key, size_t key_len, int64_t *priority, treap_bytes_t *value);
+int treap_get_i64(const treap_t *treap, int64_t key, int64_t *priority, treap_bytes_t *value);
+
+/* Remove the entry of key, handing over its priority and value; either may be NULL. */
+int treap_remove(treap_t *treap, const uint8_t *key, size_t key_len, int64_t *priority, treap_bytes_t *value);
+int treap_remove_i64(treap_t *treap, int64_t key, int64_t *priority, treap_bytes_t *value);
+
+/* Removes the top entry into *entry. */
+int treap_pop(treap_t *treap, treap_entry_t *entry);
+
+/* Decodes a key made by the _i64 functions. */
+int treap_key_i64(const uint8_t *key, size_t key_len, int64_t *out);
+
+/* Frees *bytes and empties it; NULL and empty buffers are ignored. */
+void treap_bytes_free(treap_bytes_t *bytes);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,100 @@
+use core::ops::RangeBounds;
+
+use rand::{RngCore,SeedableRng};
+use rand::rngs::StdRng;
+
+use crate::{Treap,Error,PrioritySource};
+#[cfg(feature = "std")]
+use crate::priority::entropy_rng;
+use crate::map::{Iter,Range};
+
+/// Ordered map that reorganises itself toward the access pattern: every hit draws a fresh
+/// priority and keeps it if it beats the current one, so frequently read keys drift up
+/// toward the root.
+#[derive(Debug)]
+pub struct AdaptiveMap<K,V,R = StdRng> {
+    treap: Treap<K,u64,V>,
+    source: R,
+}
+#[cfg(feature = "std")]
+impl<K: PartialOrd,V> Default for AdaptiveMap<K,V> {
+    fn default() -> AdaptiveMap<K,V> {
+        AdaptiveMap::new()
+    }
+}
+#[cfg(feature = "std")]
+impl<K: PartialOrd,V> AdaptiveMap<K,V> {
+    pub fn new() -> AdaptiveMap<K,V> {
+        AdaptiveMap::with_rng(entropy_rng())
+    }
+}
+impl<K: PartialOrd,V,R: SeedableRng + RngCore> AdaptiveMap<K,V,R> {
+    pub fn with_seed(seed: u64) -> AdaptiveMap<K,V,R> {
+        AdaptiveMap::with_rng(R::seed_from_u64(seed))
+    }
+}
+impl<K: PartialOrd,V,R: RngCore> AdaptiveMap<K,V,R> {
+    pub fn with_rng(rng: R) -> AdaptiveMap<K,V,R> {
+        AdaptiveMap::with_source(rng)
+    }
+}
+impl<K: PartialOrd,V,R: PrioritySource<K>> AdaptiveMap<K,V,R> {
+    pub fn with_source(source: R) -> AdaptiveMap<K,V,R> {
+        AdaptiveMap { treap: Treap::new(), source }
+    }
+    pub fn len(&self) -> usize {
+        self.treap.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.treap.is_empty()
+    }
+    pub fn insert(&mut self, key: K, value: V) -> Result<Option<V>,Error> {
+        let priority = self.source.priority(&key);
+        Ok(self.treap.insert(key,priority,value)?.map(|(_,v)| v))
+    }
+    pub fn remove(&mut self, key: &K) -> Result<Option<V>,Error> {
+        Ok(self.treap.remove(key)?.map(|(_,v)| v))
+    }
+    /// Looks up an entry and promotes it.
+    pub fn get(&mut self, key: &K) -> Result<Option<&V>,Error> {
+        Ok(self.get_mut(key)?.map(|v| &*v))
+    }
+    pub fn get_mut(&mut self, key: &K) -> Result<Option<&mut V>,Error> {
+        self.touch(key)?;
+        Ok(self.treap.get_mut(key)?.map(|(_,v)| v))
+    }
+    /// Looks up an entry without reorganising.
+    pub fn peek(&self, key: &K) -> Result<Option<&V>,Error> {
+        Ok(self.treap.get(key)?.map(|(_,v)| v))
+    }
+    pub fn contains_key(&self, key: &K) -> Result<bool,Error> {
+        Ok(self.treap.priority(key)?.is_some())
+    }
+    pub fn depth(&self) -> Result<usize,Error> {
+        self.treap.depth()
+    }
+    pub fn iter(&self) -> Iter<'_,K,V> {
+        Iter { iter: self.treap.iter() }
+    }
+    pub fn range<B: RangeBounds<K>>(&self, range: B) -> Range<'_,K,V> {
+        Range { iter: self.treap.range(range) }
+    }
+    fn touch(&mut self, key: &K) -> Result<(),Error> {
+        let current = match self.treap.priority(key)? {
+            Some(p) => *p,
+            None => return Ok(()),
+        };
+        let drawn = self.source.priority(key);
+        if drawn > current { self.treap.prioritize(key,drawn)?; }
+        Ok(())
+    }
+}
+
+impl<'t,K: PartialOrd,V,R: PrioritySource<K>> IntoIterator for &'t AdaptiveMap<K,V,R> {
+    type Item = (&'t K, &'t V);
+    type IntoIter = Iter<'t,K,V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
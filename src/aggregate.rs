@@ -0,0 +1,54 @@
+use core::ops::Add;
+
+/// Summary of the values in a subtree, cached in every node and recomputed whenever the
+/// subtree changes shape. `()` keeps nothing and is the default.
+pub trait Aggregate<V> {
+    fn from_value(value: &V) -> Self;
+    fn combine(left: &Self, right: &Self) -> Self; // left covers smaller keys
+}
+impl<V> Aggregate<V> for () {
+    fn from_value(_value: &V) {}
+    fn combine(_left: &(), _right: &()) {}
+}
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub struct MinMax<V> {
+    pub min: V,
+    pub max: V,
+}
+impl<V: Ord + Clone> Aggregate<V> for MinMax<V> {
+    fn from_value(value: &V) -> MinMax<V> {
+        MinMax { min: value.clone(), max: value.clone() }
+    }
+    fn combine(left: &MinMax<V>, right: &MinMax<V>) -> MinMax<V> {
+        MinMax {
+            min: core::cmp::min(&left.min,&right.min).clone(),
+            max: core::cmp::max(&left.max,&right.max).clone(),
+        }
+    }
+}
+
+/// Sum of the values in the subtree, with `V`'s own `+`: integer sums that overflow panic
+/// in debug builds, so values that could get there want a wrapping or saturating type.
+#[derive(Debug,Clone,Copy,Default,PartialEq,Eq)]
+pub struct Sum<V>(pub V);
+impl<V: Add<Output = V> + Clone> Aggregate<V> for Sum<V> {
+    fn from_value(value: &V) -> Sum<V> {
+        Sum(value.clone())
+    }
+    fn combine(left: &Sum<V>, right: &Sum<V>) -> Sum<V> {
+        Sum(left.0.clone() + right.0.clone())
+    }
+}
+
+/// Number of entries in the subtree.
+#[derive(Debug,Clone,Copy,Default,PartialEq,Eq)]
+pub struct Count(pub usize);
+impl<V> Aggregate<V> for Count {
+    fn from_value(_value: &V) -> Count {
+        Count(1)
+    }
+    fn combine(left: &Count, right: &Count) -> Count {
+        Count(left.0 + right.0)
+    }
+}
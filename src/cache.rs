@@ -0,0 +1,99 @@
+use core::cmp::Reverse;
+use alloc::vec::Vec;
+
+use crate::{Treap,Error};
+
+#[derive(Debug,Default,Clone,Copy,PartialEq,Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+}
+
+/// Capacity-bounded LRU cache. Priorities are `Reverse(last access tick)`, so the root
+/// is always the least recently used entry and eviction is a `pop`.
+#[derive(Debug)]
+pub struct TreapCache<K,V> {
+    treap: Treap<K,Reverse<u64>,V>,
+    capacity: usize,
+    tick: u64,
+    stats: CacheStats,
+}
+impl<K: PartialOrd,V> TreapCache<K,V> {
+    pub fn new(capacity: usize) -> TreapCache<K,V> {
+        TreapCache { treap: Treap::new(), capacity, tick: 0, stats: CacheStats::default() }
+    }
+    pub fn len(&self) -> usize {
+        self.treap.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.treap.is_empty()
+    }
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+    pub fn reset_stats(&mut self) {
+        self.stats = CacheStats::default();
+    }
+    fn next_tick(&mut self) -> Reverse<u64> {
+        self.tick += 1;
+        Reverse(self.tick)
+    }
+    /// Inserts or replaces an entry, marking it most recently used;
+    /// returns the entry evicted to stay within capacity.
+    pub fn insert(&mut self, key: K, value: V) -> Result<Option<(K,V)>,Error> {
+        let tick = self.next_tick();
+        self.treap.insert(key,tick,value)?;
+        let mut evicted = self.shrink()?;
+        Ok(evicted.pop())
+    }
+    /// Looks up an entry and marks it most recently used.
+    pub fn get(&mut self, key: &K) -> Result<Option<&V>,Error> {
+        Ok(self.get_mut(key)?.map(|v| &*v))
+    }
+    pub fn get_mut(&mut self, key: &K) -> Result<Option<&mut V>,Error> {
+        let tick = self.next_tick();
+        match self.treap.prioritize(key,tick)? {
+            Some(_) => {
+                self.stats.hits += 1;
+                Ok(self.treap.get_mut(key)?.map(|(_,v)| v))
+            },
+            None => {
+                self.stats.misses += 1;
+                Ok(None)
+            },
+        }
+    }
+    /// Looks up an entry without touching it or the counters.
+    pub fn peek(&self, key: &K) -> Result<Option<&V>,Error> {
+        Ok(self.treap.get(key)?.map(|(_,v)| v))
+    }
+    pub fn contains(&self, key: &K) -> Result<bool,Error> {
+        Ok(self.treap.priority(key)?.is_some())
+    }
+    pub fn remove(&mut self, key: &K) -> Result<Option<V>,Error> {
+        Ok(self.treap.remove(key)?.map(|(_,v)| v))
+    }
+    /// Least recently used entry, the next one to be evicted.
+    pub fn peek_lru(&self) -> Result<Option<(&K,&V)>,Error> {
+        Ok(self.treap.peek()?.map(|(k,_,v)| (k,v)))
+    }
+    pub fn set_capacity(&mut self, capacity: usize) -> Result<Vec<(K,V)>,Error> {
+        self.capacity = capacity;
+        self.shrink()
+    }
+    fn shrink(&mut self) -> Result<Vec<(K,V)>,Error> {
+        let mut evicted = Vec::new();
+        while self.treap.len() > self.capacity {
+            match self.treap.pop()? {
+                Some((k,_,v)) => evicted.push((k,v)),
+                None => break,
+            }
+            self.stats.evictions += 1;
+        }
+        Ok(evicted)
+    }
+}
@@ -0,0 +1,174 @@
+use core::ops::RangeBounds;
+use core::hash::Hash;
+
+use rand::{RngCore,SeedableRng};
+use rand::rngs::StdRng;
+
+use crate::{TreapMap,Error,PrioritySource,KeyHash};
+#[cfg(feature = "std")]
+use crate::priority::entropy_rng;
+use crate::map;
+
+/// Ordered multiset: every key carries an occurrence count.
+#[derive(Debug)]
+pub struct TreapCounter<K,R = StdRng> {
+    map: TreapMap<K,usize,R>,
+    total: usize,
+}
+#[cfg(feature = "std")]
+impl<K: PartialOrd> Default for TreapCounter<K> {
+    fn default() -> TreapCounter<K> {
+        TreapCounter::new()
+    }
+}
+#[cfg(feature = "std")]
+impl<K: PartialOrd> TreapCounter<K> {
+    pub fn new() -> TreapCounter<K> {
+        TreapCounter::with_rng(entropy_rng())
+    }
+}
+impl<K: PartialOrd,R: SeedableRng + RngCore> TreapCounter<K,R> {
+    pub fn with_seed(seed: u64) -> TreapCounter<K,R> {
+        TreapCounter::with_rng(R::seed_from_u64(seed))
+    }
+}
+impl<K: PartialOrd,R: RngCore> TreapCounter<K,R> {
+    pub fn with_rng(rng: R) -> TreapCounter<K,R> {
+        TreapCounter::with_source(rng)
+    }
+}
+impl<K: PartialOrd + Hash> TreapCounter<K,KeyHash> {
+    pub fn with_key_hash(seed: u64) -> TreapCounter<K,KeyHash> {
+        TreapCounter::with_source(KeyHash::new(seed))
+    }
+}
+impl<K: PartialOrd,R: PrioritySource<K>> TreapCounter<K,R> {
+    pub fn with_source(source: R) -> TreapCounter<K,R> {
+        TreapCounter { map: TreapMap::with_source(source), total: 0 }
+    }
+    /// Total number of occurrences.
+    pub fn len(&self) -> usize {
+        self.total
+    }
+    pub fn is_empty(&self) -> bool {
+        self.total == 0
+    }
+    /// Number of distinct keys.
+    pub fn distinct(&self) -> usize {
+        self.map.len()
+    }
+    pub fn count(&self, key: &K) -> Result<usize,Error> {
+        Ok(self.map.get(key)?.copied().unwrap_or(0))
+    }
+    pub fn insert(&mut self, key: K) -> Result<usize,Error> {
+        self.insert_many(key,1)
+    }
+    pub fn insert_many(&mut self, key: K, n: usize) -> Result<usize,Error> {
+        if n == 0 { return self.count(&key); }
+        // no count exceeds the total, so checking the total covers them all
+        let total = self.total.checked_add(n).ok_or(Error::Overflow)?;
+        let count = match self.map.get_mut(&key)? {
+            Some(count) => {
+                *count += n;
+                *count
+            },
+            None => {
+                self.map.insert(key,n)?;
+                n
+            },
+        };
+        self.total = total;
+        Ok(count)
+    }
+    pub fn remove(&mut self, key: &K) -> Result<bool,Error> {
+        Ok(self.remove_many(key,1)? > 0)
+    }
+    /// Removes up to `n` occurrences, returns how many were actually removed.
+    pub fn remove_many(&mut self, key: &K, n: usize) -> Result<usize,Error> {
+        let (removed,emptied) = match self.map.get_mut(key)? {
+            Some(count) => {
+                let removed = usize::min(*count,n);
+                *count -= removed;
+                (removed,*count == 0)
+            },
+            None => (0,false),
+        };
+        if emptied { self.map.remove(key)?; }
+        self.total -= removed;
+        Ok(removed)
+    }
+    pub fn remove_all(&mut self, key: &K) -> Result<usize,Error> {
+        let removed = self.map.remove(key)?.unwrap_or(0);
+        self.total -= removed;
+        Ok(removed)
+    }
+    /// Distinct keys with their counts, in key order.
+    pub fn iter(&self) -> Iter<'_,K> {
+        Iter { iter: self.map.iter() }
+    }
+    pub fn range<B: RangeBounds<K>>(&self, range: B) -> Range<'_,K> {
+        Range { iter: self.map.range(range) }
+    }
+    /// Every key repeated as many times as it was counted, in key order.
+    pub fn elements(&self) -> Elements<'_,K> {
+        Elements { iter: self.map.iter(), current: None }
+    }
+}
+
+impl<'t,K: PartialOrd,R: PrioritySource<K>> IntoIterator for &'t TreapCounter<K,R> {
+    type Item = (&'t K, usize);
+    type IntoIter = Iter<'t,K>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct Iter<'t,K> {
+    iter: map::Iter<'t,K,usize>,
+}
+impl<'t,K> Iterator for Iter<'t,K> {
+    type Item = (&'t K, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|(k,c)| (k,*c))
+    }
+    fn size_hint(&self) -> (usize,Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+pub struct Range<'t,K> {
+    iter: map::Range<'t,K,usize>,
+}
+impl<'t,K> Iterator for Range<'t,K> {
+    type Item = (&'t K, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|(k,c)| (k,*c))
+    }
+    fn size_hint(&self) -> (usize,Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+pub struct Elements<'t,K> {
+    iter: map::Iter<'t,K,usize>,
+    current: Option<(&'t K, usize)>,
+}
+impl<'t,K> Iterator for Elements<'t,K> {
+    type Item = &'t K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((key,left)) = &mut self.current {
+                if *left > 0 {
+                    *left -= 1;
+                    return Some(*key);
+                }
+            }
+            let (key,count) = self.iter.next()?;
+            self.current = Some((key,*count));
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use core::ops::RangeBounds;
+use alloc::vec::Vec;
+
+use crate::{PersistentTreap,Change,Error};
+use crate::persistent::{Iter,Range};
+
+/// Mutable treap whose `clone()` is O(1): both sides share all nodes, and a later update
+/// on either side copies only the nodes on its path that are still shared. A node owned
+/// by a single treap is updated in place.
+#[derive(Debug)]
+pub struct CowTreap<K,P,V> {
+    treap: PersistentTreap<K,P,V>,
+    checkpoints: Vec<(usize,PersistentTreap<K,P,V>)>,
+    next_checkpoint: usize,
+}
+impl<K,P,V> Clone for CowTreap<K,P,V> {
+    fn clone(&self) -> CowTreap<K,P,V> { // checkpoints stay with the original
+        CowTreap::from(self.treap.clone())
+    }
+}
+impl<K,P,V> Default for CowTreap<K,P,V> {
+    fn default() -> CowTreap<K,P,V> {
+        CowTreap::new()
+    }
+}
+impl<K,P,V> CowTreap<K,P,V> {
+    pub fn new() -> CowTreap<K,P,V> {
+        CowTreap::from(PersistentTreap::new())
+    }
+    pub fn len(&self) -> usize {
+        self.treap.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.treap.is_empty()
+    }
+    pub fn peek(&self) -> Option<(&K,&P,&V)> {
+        self.treap.peek()
+    }
+    pub fn iter(&self) -> Iter<'_,K,P,V> {
+        self.treap.iter()
+    }
+    /// Current state as a persistent version, sharing all nodes.
+    pub fn snapshot(&self) -> PersistentTreap<K,P,V> {
+        self.treap.clone()
+    }
+    /// Remembers the current state in O(1). Updates made while a checkpoint is held copy
+    /// the nodes they touch the first time, so that `rollback` can return to it.
+    pub fn checkpoint(&mut self) -> Checkpoint {
+        let id = self.next_checkpoint;
+        self.next_checkpoint += 1;
+        self.checkpoints.push((id,self.treap.clone()));
+        Checkpoint(id)
+    }
+    /// Restores the state at `checkpoint`, dropping it and every checkpoint taken after it.
+    pub fn rollback(&mut self, checkpoint: Checkpoint) -> Result<(),Error> {
+        let position = self.position(checkpoint)?;
+        if let Some((_,treap)) = self.checkpoints.drain(position ..).next() {
+            self.treap = treap;
+        }
+        Ok(())
+    }
+    /// Keeps the current state, dropping `checkpoint` and every checkpoint taken after it.
+    pub fn release(&mut self, checkpoint: Checkpoint) -> Result<(),Error> {
+        let position = self.position(checkpoint)?;
+        self.checkpoints.truncate(position);
+        Ok(())
+    }
+    /// Applies the updates `f` makes if it returns `Ok`, and none of them otherwise. `f`
+    /// works on an O(1) copy, so an error or a panic part way leaves `self` untouched.
+    pub fn transaction<T, E, F: FnOnce(&mut CowTreap<K,P,V>) -> Result<T,E>>(&mut self, f: F) -> Result<T,E> {
+        let mut txn = CowTreap::from(self.treap.clone());
+        let result = f(&mut txn)?;
+        self.treap = txn.treap;
+        Ok(result)
+    }
+    fn position(&self, checkpoint: Checkpoint) -> Result<usize,Error> {
+        self.checkpoints.iter().rposition(|(id,_)| *id == checkpoint.0).ok_or(Error::Checkpoint(checkpoint.0))
+    }
+}
+impl<K: PartialOrd,P,V> CowTreap<K,P,V> {
+    pub fn get(&self, key: &K) -> Option<(&P,&V)> {
+        self.treap.get(key)
+    }
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.treap.contains_key(key)
+    }
+    pub fn range<B: RangeBounds<K>>(&self, range: B) -> Range<'_,K,P,V> {
+        self.treap.range(range)
+    }
+}
+impl<K: PartialOrd + Clone,P: PartialOrd + Clone,V: Clone> CowTreap<K,P,V> {
+    pub fn insert(&mut self, key: K, priority: P, value: V) -> Option<(P,V)> {
+        self.treap.insert_mut(key,priority,value)
+    }
+    pub fn remove(&mut self, key: &K) -> Option<(P,V)> {
+        self.treap.remove_mut(key)
+    }
+    pub fn get_mut(&mut self, key: &K) -> Option<(&P,&mut V)> {
+        self.treap.get_mut(key)
+    }
+}
+impl<K: PartialOrd + Clone,P: PartialOrd + Clone,V: PartialEq + Clone> CowTreap<K,P,V> {
+    /// Changes turning `self` into `other`, skipping the nodes they still share.
+    pub fn diff(&self, other: &CowTreap<K,P,V>) -> Vec<Change<K,P,V>> {
+        self.treap.diff(&other.treap)
+    }
+}
+
+impl<K,P,V> From<PersistentTreap<K,P,V>> for CowTreap<K,P,V> {
+    fn from(treap: PersistentTreap<K,P,V>) -> CowTreap<K,P,V> {
+        CowTreap { treap, checkpoints: Vec::new(), next_checkpoint: 0 }
+    }
+}
+
+impl<'t,K,P,V> IntoIterator for &'t CowTreap<K,P,V> {
+    type Item = (&'t K, &'t P, &'t V);
+    type IntoIter = Iter<'t,K,P,V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Token from `CowTreap::checkpoint`.
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub struct Checkpoint(usize);
@@ -0,0 +1,102 @@
+use core::borrow::Borrow;
+use alloc::vec::Vec;
+
+use crate::{Treap,Id,NodePtr,Aggregate,HeapPolicy,MaxPolicy,Error,IndexError};
+
+/// Slot of one key in a `Treap`, looked up by a borrowed form of the key: an owned key is
+/// only made, with `From`, when a vacant entry is filled.
+pub enum EntryRef<'t,'q,K,Q: ?Sized,P,V,A = (),H = MaxPolicy> {
+    Occupied(OccupiedEntryRef<'t,K,P,V,A,H>),
+    Vacant(VacantEntryRef<'t,'q,K,Q,P,V,A,H>),
+}
+
+pub struct OccupiedEntryRef<'t,K,P,V,A = (),H = MaxPolicy> {
+    treap: &'t mut Treap<K,P,V,A,H>,
+    path: Vec<Id>, // from the root down to the entry
+}
+
+pub struct VacantEntryRef<'t,'q,K,Q: ?Sized,P,V,A = (),H = MaxPolicy> {
+    treap: &'t mut Treap<K,P,V,A,H>,
+    key: &'q Q,
+}
+
+impl<K: PartialOrd,P: PartialOrd,V,A: Aggregate<V>,H: HeapPolicy> Treap<K,P,V,A,H> {
+    pub fn entry_ref<'t,'q,Q: ?Sized + PartialOrd>(&'t mut self, key: &'q Q) -> Result<EntryRef<'t,'q,K,Q,P,V,A,H>,Error> where K: Borrow<Q> {
+        let mut path = Vec::new();
+        let mut node = self.root;
+        while let Some(id) = node {
+            let entry = self.index.get(&node).map_err(Error::Index)?;
+            path.push(id);
+            let here: &Q = entry.key.borrow();
+            if here == key { return Ok(EntryRef::Occupied(OccupiedEntryRef { treap: self, path })); }
+            node = if here > key { entry.left } else { entry.right };
+        }
+        Ok(EntryRef::Vacant(VacantEntryRef { treap: self, key }))
+    }
+}
+
+impl<'t,'q,K: PartialOrd,Q: ?Sized,P: PartialOrd,V,A: Aggregate<V>,H: HeapPolicy> EntryRef<'t,'q,K,Q,P,V,A,H> {
+    /// Inserts the entry `f` makes if the key is vacant.
+    pub fn or_insert_with<F: FnOnce() -> (P,V)>(self, f: F) -> Result<(),Error> where K: From<&'q Q> {
+        match self {
+            EntryRef::Occupied(_) => Ok(()),
+            EntryRef::Vacant(vacant) => {
+                let (priority,value) = f();
+                vacant.insert(priority,value)
+            },
+        }
+    }
+}
+
+impl<'t,K: PartialOrd,P: PartialOrd,V,A: Aggregate<V>,H: HeapPolicy> OccupiedEntryRef<'t,K,P,V,A,H> {
+    fn node(&self) -> NodePtr {
+        self.path.last().copied()
+    }
+    pub fn get(&self) -> Result<(&K,&P,&V),Error> {
+        let entry = self.treap.index.get(&self.node()).map_err(Error::Index)?;
+        Ok((&entry.key,&entry.priority,&entry.value))
+    }
+    /// Edits the value in place and refreshes the aggregates above it, as `update_value`.
+    pub fn update<F: FnOnce(&mut V)>(&mut self, f: F) -> Result<(),Error> {
+        f(&mut self.treap.index.get_mut(&self.node()).map_err(Error::Index)?.value);
+        for &id in self.path.iter().rev() {
+            Treap::update(&mut self.treap.index,Some(id)).map_err(Error::Index)?;
+        }
+        Ok(())
+    }
+    pub fn remove(self) -> Result<(K,P,V),Error> {
+        let OccupiedEntryRef { treap, mut path } = self;
+        let node = path.pop().ok_or(Error::Index(IndexError::None))?;
+        let removed = treap.index.remove(&Some(node)).map_err(Error::Index)?;
+        let rest = Treap::<K,P,V,A,H>::merge_nodes(&mut treap.index,removed.left,removed.right).map_err(Error::Index)?;
+        match path.last() {
+            None => treap.root = rest,
+            Some(&parent) => {
+                let parent = treap.index.get_mut(&Some(parent)).map_err(Error::Index)?;
+                if parent.left == Some(node) { parent.left = rest; } else { parent.right = rest; }
+            },
+        }
+        for &id in path.iter().rev() {
+            Treap::update(&mut treap.index,Some(id)).map_err(Error::Index)?;
+        }
+        Ok((removed.key,removed.priority,removed.value))
+    }
+}
+impl<'t,K,P,V,H> OccupiedEntryRef<'t,K,P,V,(),H> {
+    // plain treaps only, like `get_mut`
+    pub fn into_mut(self) -> Result<&'t mut V,Error> {
+        let node = self.path.last().copied();
+        Ok(&mut self.treap.index.get_mut(&node).map_err(Error::Index)?.value)
+    }
+}
+
+impl<'t,'q,K: PartialOrd,Q: ?Sized,P: PartialOrd,V,A: Aggregate<V>,H: HeapPolicy> VacantEntryRef<'t,'q,K,Q,P,V,A,H> {
+    pub fn key(&self) -> &'q Q {
+        self.key
+    }
+    /// Makes the owned key and inserts the entry.
+    pub fn insert(self, priority: P, value: V) -> Result<(),Error> where K: From<&'q Q> {
+        self.treap.insert(K::from(self.key),priority,value)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,145 @@
+use core::cell::Cell;
+#[cfg(feature = "std")]
+use std::time::{Instant,Duration};
+use alloc::vec::Vec;
+
+use crate::{Treap,Error};
+
+pub trait Clock {
+    type Instant: PartialOrd;
+    type Duration;
+    fn now(&self) -> Self::Instant;
+    fn after(&self, ttl: Self::Duration) -> Self::Instant;
+}
+
+#[cfg(feature = "std")]
+#[derive(Debug,Default,Clone,Copy)]
+pub struct SystemClock;
+#[cfg(feature = "std")]
+impl Clock for SystemClock {
+    type Instant = Instant;
+    type Duration = Duration;
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+    fn after(&self, mut ttl: Duration) -> Instant {
+        // a deadline past what `Instant` holds, as with `Duration::MAX` for "never", is
+        // pulled in until it fits rather than overflowing
+        let now = Instant::now();
+        loop {
+            match now.checked_add(ttl) {
+                Some(deadline) => return deadline,
+                None => ttl /= 2,
+            }
+        }
+    }
+}
+
+/// Clock that only moves when told to, for tests and simulations.
+#[derive(Debug,Default,Clone)]
+pub struct ManualClock {
+    now: Cell<u64>,
+}
+impl ManualClock {
+    pub fn new(now: u64) -> ManualClock {
+        ManualClock { now: Cell::new(now) }
+    }
+    pub fn set(&self, now: u64) {
+        self.now.set(now);
+    }
+    pub fn advance(&self, d: u64) {
+        self.now.set(self.now.get().saturating_add(d));
+    }
+}
+impl Clock for ManualClock {
+    type Instant = u64;
+    type Duration = u64;
+    fn now(&self) -> u64 {
+        self.now.get()
+    }
+    fn after(&self, ttl: u64) -> u64 {
+        self.now.get().saturating_add(ttl)
+    }
+}
+
+/// Treap keyed by `K` whose priorities are deadlines; entries past their deadline are
+/// invisible to lookups and are evicted by `expire`.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct ExpiringTreap<K,V,C: Clock = SystemClock> {
+    treap: Treap<K,C::Instant,V>,
+    clock: C,
+}
+/// Treap keyed by `K` whose priorities are deadlines; without `std` there is no system
+/// clock to default to.
+#[cfg(not(feature = "std"))]
+#[derive(Debug)]
+pub struct ExpiringTreap<K,V,C: Clock> {
+    treap: Treap<K,C::Instant,V>,
+    clock: C,
+}
+#[cfg(feature = "std")]
+impl<K: PartialOrd,V> Default for ExpiringTreap<K,V> {
+    fn default() -> ExpiringTreap<K,V> {
+        ExpiringTreap::new()
+    }
+}
+#[cfg(feature = "std")]
+impl<K: PartialOrd,V> ExpiringTreap<K,V> {
+    pub fn new() -> ExpiringTreap<K,V> {
+        ExpiringTreap::with_clock(SystemClock)
+    }
+}
+impl<K: PartialOrd,V,C: Clock> ExpiringTreap<K,V,C> {
+    pub fn with_clock(clock: C) -> ExpiringTreap<K,V,C> {
+        ExpiringTreap { treap: Treap::new(), clock }
+    }
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+    /// Number of stored entries, including the ones past due but not yet expired.
+    pub fn len(&self) -> usize {
+        self.treap.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.treap.is_empty()
+    }
+    pub fn insert(&mut self, key: K, deadline: C::Instant, value: V) -> Result<Option<(C::Instant,V)>,Error> {
+        self.treap.insert(key,deadline,value)
+    }
+    pub fn insert_ttl(&mut self, key: K, ttl: C::Duration, value: V) -> Result<Option<(C::Instant,V)>,Error> {
+        let deadline = self.clock.after(ttl);
+        self.treap.insert(key,deadline,value)
+    }
+    pub fn get(&self, key: &K) -> Result<Option<&V>,Error> {
+        let now = self.clock.now();
+        Ok(match self.treap.get(key)? {
+            Some((deadline,value)) if *deadline >= now => Some(value),
+            _ => None,
+        })
+    }
+    pub fn get_mut(&mut self, key: &K) -> Result<Option<&mut V>,Error> {
+        let now = self.clock.now();
+        Ok(match self.treap.get_mut(key)? {
+            Some((deadline,value)) if *deadline >= now => Some(value),
+            _ => None,
+        })
+    }
+    pub fn deadline(&self, key: &K) -> Result<Option<&C::Instant>,Error> {
+        self.treap.priority(key)
+    }
+    pub fn prolong(&mut self, key: &K, deadline: C::Instant) -> Result<Option<C::Instant>,Error> {
+        self.treap.prioritize(key,deadline)
+    }
+    pub fn remove(&mut self, key: &K) -> Result<Option<(C::Instant,V)>,Error> {
+        self.treap.remove(key)
+    }
+    /// Evicts everything with a deadline before the clock's current time.
+    pub fn expire(&mut self) -> Result<Vec<(K,C::Instant,V)>,Error> {
+        let now = self.clock.now();
+        self.treap.drain_below(&now)
+    }
+    pub fn expire_at(&mut self, now: &C::Instant) -> Result<Vec<(K,C::Instant,V)>,Error> {
+        self.treap.drain_below(now)
+    }
+}
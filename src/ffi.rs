@@ -0,0 +1,234 @@
+// C ABI for native extensions in other languages; the declarations are in
+// `include/treap.h`. Build the library with
+// `cargo rustc --release --features ffi --crate-type staticlib` (or `cdylib`).
+
+use core::{ptr,slice};
+use core::ffi::c_int;
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use crate::Treap;
+
+/// Treap behind the C `treap_t`. Keys order as bytes; the `_i64` functions encode an
+/// integer key as 8 big-endian bytes with the sign bit flipped, so integer keys order as
+/// integers, and `treap_key_i64` turns such a key back. Buffers handed out belong to the
+/// caller, who frees them with `treap_bytes_free`.
+pub type ByteTreap = Treap<Vec<u8>,i64,Vec<u8>>;
+
+pub const TREAP_OK: c_int = 0;
+/// No such key, or no entry to pop.
+pub const TREAP_ABSENT: c_int = 1;
+/// Null pointer where one isn't allowed, or a corrupted treap.
+pub const TREAP_ERROR: c_int = -1;
+
+/// Owned buffer; `ptr` is null only when `len` is 0.
+#[repr(C)]
+#[derive(Debug)]
+pub struct TreapBytes {
+    pub ptr: *mut u8,
+    pub len: usize,
+}
+impl TreapBytes {
+    fn empty() -> TreapBytes {
+        TreapBytes { ptr: ptr::null_mut(), len: 0 }
+    }
+    fn from_vec(bytes: Vec<u8>) -> TreapBytes {
+        if bytes.is_empty() { return TreapBytes::empty(); }
+        let len = bytes.len();
+        TreapBytes { ptr: Box::into_raw(bytes.into_boxed_slice()) as *mut u8, len }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct TreapEntry {
+    pub key: TreapBytes,
+    pub priority: i64,
+    pub value: TreapBytes,
+}
+
+fn encode_i64(key: i64) -> Vec<u8> {
+    ((key as u64) ^ (1 << 63)).to_be_bytes().to_vec()
+}
+
+// `len` bytes at `data`, which may be null for an empty buffer
+unsafe fn bytes<'a>(data: *const u8, len: usize) -> Option<&'a [u8]> {
+    match (data.is_null(),len) {
+        (_,0) => Some(&[]),
+        (true,_) => None,
+        (false,_) => Some(slice::from_raw_parts(data,len)),
+    }
+}
+
+// made only when wanted: a `TreapBytes` dropped unwritten would leak its buffer
+unsafe fn write<T,F: FnOnce() -> T>(out: *mut T, value: F) {
+    if !out.is_null() { out.write(value()); }
+}
+
+/// New empty treap, freed with `treap_free`.
+#[no_mangle]
+pub extern "C" fn treap_new() -> *mut ByteTreap {
+    Box::into_raw(Box::new(Treap::new()))
+}
+
+/// # Safety
+/// `treap` must come from `treap_new` and not be used again; null is ignored.
+#[no_mangle]
+pub unsafe extern "C" fn treap_free(treap: *mut ByteTreap) {
+    if !treap.is_null() { drop(Box::from_raw(treap)); }
+}
+
+/// # Safety
+/// `treap` must be null or live.
+#[no_mangle]
+pub unsafe extern "C" fn treap_len(treap: *const ByteTreap) -> usize {
+    treap.as_ref().map_or(0,|treap| treap.len())
+}
+
+/// Inserts or replaces the entry of `key`.
+///
+/// # Safety
+/// `treap` must be live; `key` and `value` must point to `key_len` and `value_len`
+/// readable bytes, or may be null when the length is 0.
+#[no_mangle]
+pub unsafe extern "C" fn treap_insert(treap: *mut ByteTreap, key: *const u8, key_len: usize, priority: i64, value: *const u8, value_len: usize) -> c_int {
+    match bytes(key,key_len) {
+        Some(key) => insert(treap,key.to_vec(),priority,value,value_len),
+        None => TREAP_ERROR,
+    }
+}
+
+/// # Safety
+/// As `treap_insert`.
+#[no_mangle]
+pub unsafe extern "C" fn treap_insert_i64(treap: *mut ByteTreap, key: i64, priority: i64, value: *const u8, value_len: usize) -> c_int {
+    insert(treap,encode_i64(key),priority,value,value_len)
+}
+
+unsafe fn insert(treap: *mut ByteTreap, key: Vec<u8>, priority: i64, value: *const u8, value_len: usize) -> c_int {
+    match (treap.as_mut(),bytes(value,value_len)) {
+        (Some(treap),Some(value)) => match treap.insert(key,priority,value.to_vec()) {
+            Ok(_) => TREAP_OK,
+            Err(_) => TREAP_ERROR,
+        },
+        _ => TREAP_ERROR,
+    }
+}
+
+/// Copies out the priority and value of `key`; either out-pointer may be null to skip it.
+///
+/// # Safety
+/// As `treap_insert` for `treap` and `key`; the out-pointers must be null or writable.
+#[no_mangle]
+pub unsafe extern "C" fn treap_get(treap: *const ByteTreap, key: *const u8, key_len: usize, priority: *mut i64, value: *mut TreapBytes) -> c_int {
+    match bytes(key,key_len) {
+        Some(key) => get(treap,&key.to_vec(),priority,value),
+        None => TREAP_ERROR,
+    }
+}
+
+/// # Safety
+/// As `treap_get`.
+#[no_mangle]
+pub unsafe extern "C" fn treap_get_i64(treap: *const ByteTreap, key: i64, priority: *mut i64, value: *mut TreapBytes) -> c_int {
+    get(treap,&encode_i64(key),priority,value)
+}
+
+unsafe fn get(treap: *const ByteTreap, key: &Vec<u8>, priority: *mut i64, value: *mut TreapBytes) -> c_int {
+    let treap = match treap.as_ref() {
+        Some(treap) => treap,
+        None => return TREAP_ERROR,
+    };
+    match treap.get(key) {
+        Ok(Some((p,v))) => {
+            write(priority,|| *p);
+            write(value,|| TreapBytes::from_vec(v.clone()));
+            TREAP_OK
+        },
+        Ok(None) => TREAP_ABSENT,
+        Err(_) => TREAP_ERROR,
+    }
+}
+
+/// Removes the entry of `key`, handing its priority and value to the out-pointers, which
+/// may be null to drop them.
+///
+/// # Safety
+/// As `treap_get`.
+#[no_mangle]
+pub unsafe extern "C" fn treap_remove(treap: *mut ByteTreap, key: *const u8, key_len: usize, priority: *mut i64, value: *mut TreapBytes) -> c_int {
+    match bytes(key,key_len) {
+        Some(key) => remove(treap,&key.to_vec(),priority,value),
+        None => TREAP_ERROR,
+    }
+}
+
+/// # Safety
+/// As `treap_get`.
+#[no_mangle]
+pub unsafe extern "C" fn treap_remove_i64(treap: *mut ByteTreap, key: i64, priority: *mut i64, value: *mut TreapBytes) -> c_int {
+    remove(treap,&encode_i64(key),priority,value)
+}
+
+unsafe fn remove(treap: *mut ByteTreap, key: &Vec<u8>, priority: *mut i64, value: *mut TreapBytes) -> c_int {
+    let treap = match treap.as_mut() {
+        Some(treap) => treap,
+        None => return TREAP_ERROR,
+    };
+    match treap.remove(key) {
+        Ok(Some((p,v))) => {
+            write(priority,|| p);
+            write(value,|| TreapBytes::from_vec(v));
+            TREAP_OK
+        },
+        Ok(None) => TREAP_ABSENT,
+        Err(_) => TREAP_ERROR,
+    }
+}
+
+/// Removes the top entry into `entry`, whose buffers the caller then owns.
+///
+/// # Safety
+/// `treap` must be live and `entry` writable.
+#[no_mangle]
+pub unsafe extern "C" fn treap_pop(treap: *mut ByteTreap, entry: *mut TreapEntry) -> c_int {
+    let treap = match treap.as_mut() {
+        Some(treap) if !entry.is_null() => treap,
+        _ => return TREAP_ERROR,
+    };
+    match treap.pop() {
+        Ok(Some((key,priority,value))) => {
+            entry.write(TreapEntry { key: TreapBytes::from_vec(key), priority, value: TreapBytes::from_vec(value) });
+            TREAP_OK
+        },
+        Ok(None) => TREAP_ABSENT,
+        Err(_) => TREAP_ERROR,
+    }
+}
+
+/// Decodes a key made by the `_i64` functions.
+///
+/// # Safety
+/// `key` must point to `key_len` readable bytes and `out` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn treap_key_i64(key: *const u8, key_len: usize, out: *mut i64) -> c_int {
+    match bytes(key,key_len) {
+        Some(&[a,b,c,d,e,f,g,h]) if !out.is_null() => {
+            out.write((u64::from_be_bytes([a,b,c,d,e,f,g,h]) ^ (1 << 63)) as i64);
+            TREAP_OK
+        },
+        _ => TREAP_ERROR,
+    }
+}
+
+/// Frees a buffer handed out by this library and empties it; empty buffers are ignored.
+///
+/// # Safety
+/// `bytes` must be null or hold a buffer from this library not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn treap_bytes_free(bytes: *mut TreapBytes) {
+    if let Some(bytes) = bytes.as_mut() {
+        if !bytes.ptr.is_null() { drop(Box::from_raw(ptr::slice_from_raw_parts_mut(bytes.ptr,bytes.len))); }
+        *bytes = TreapBytes::empty();
+    }
+}
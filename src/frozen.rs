@@ -0,0 +1,102 @@
+use core::marker::PhantomData;
+use core::ops::RangeBounds;
+use core::slice;
+use alloc::vec::Vec;
+
+use crate::{Treap,Aggregate,HeapPolicy,MaxPolicy};
+use crate::iter::{after_start,before_end};
+
+/// Read-only snapshot of a treap as dense key-sorted arrays: lookups are binary searches
+/// over contiguous memory and iteration is a plain slice walk. `thaw` gives the treap back.
+#[derive(Debug,Clone)]
+pub struct FrozenTreap<K,P,V,A = (),H = MaxPolicy> {
+    keys: Vec<K>,
+    priorities: Vec<P>,
+    values: Vec<V>,
+    aggregate: PhantomData<A>,
+    policy: PhantomData<H>,
+}
+impl<K: PartialOrd,P: PartialOrd,V,A: Aggregate<V>,H: HeapPolicy> FrozenTreap<K,P,V,A,H> {
+    pub(crate) fn new(entries: Vec<(K,P,V)>) -> FrozenTreap<K,P,V,A,H> {
+        let mut frozen = FrozenTreap {
+            keys: Vec::with_capacity(entries.len()),
+            priorities: Vec::with_capacity(entries.len()),
+            values: Vec::with_capacity(entries.len()),
+            aggregate: PhantomData,
+            policy: PhantomData,
+        };
+        for (k,p,v) in entries {
+            frozen.keys.push(k);
+            frozen.priorities.push(p);
+            frozen.values.push(v);
+        }
+        frozen
+    }
+    /// Rebuilds the treap in O(n).
+    pub fn thaw(self) -> Treap<K,P,V,A,H> {
+        let entries = self.keys.into_iter().zip(self.priorities).zip(self.values).map(|((k,p),v)| (k,p,v));
+        Treap::build_sorted(entries)
+    }
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+    pub fn get(&self, key: &K) -> Option<(&P,&V)> {
+        let i = self.keys.partition_point(|k| k < key);
+        match self.keys.get(i) {
+            Some(k) if k == key => Some((&self.priorities[i],&self.values[i])),
+            _ => None,
+        }
+    }
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+    pub fn iter(&self) -> Iter<'_,K,P,V> {
+        self.slice(0,self.len())
+    }
+    pub fn range<B: RangeBounds<K>>(&self, range: B) -> Iter<'_,K,P,V> {
+        let start = self.keys.partition_point(|k| !after_start(k,range.start_bound()));
+        let end = self.keys.partition_point(|k| before_end(k,range.end_bound()));
+        self.slice(start,end.max(start))
+    }
+    fn slice(&self, start: usize, end: usize) -> Iter<'_,K,P,V> {
+        Iter {
+            keys: self.keys[start..end].iter(),
+            priorities: self.priorities[start..end].iter(),
+            values: self.values[start..end].iter(),
+        }
+    }
+}
+
+impl<'t,K: PartialOrd,P: PartialOrd,V,A: Aggregate<V>,H: HeapPolicy> IntoIterator for &'t FrozenTreap<K,P,V,A,H> {
+    type Item = (&'t K, &'t P, &'t V);
+    type IntoIter = Iter<'t,K,P,V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct Iter<'t,K,P,V> {
+    keys: slice::Iter<'t,K>,
+    priorities: slice::Iter<'t,P>,
+    values: slice::Iter<'t,V>,
+}
+impl<'t,K,P,V> Iterator for Iter<'t,K,P,V> {
+    type Item = (&'t K, &'t P, &'t V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some((self.keys.next()?,self.priorities.next()?,self.values.next()?))
+    }
+    fn size_hint(&self) -> (usize,Option<usize>) {
+        self.keys.size_hint()
+    }
+}
+impl<'t,K,P,V> DoubleEndedIterator for Iter<'t,K,P,V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        Some((self.keys.next_back()?,self.priorities.next_back()?,self.values.next_back()?))
+    }
+}
+impl<'t,K,P,V> ExactSizeIterator for Iter<'t,K,P,V> {}
@@ -0,0 +1,84 @@
+use crate::{Treap,Id,Index,Iter,Aggregate,HeapPolicy,Error,IndexError};
+
+/// Reference to one entry of a `Treap` that doesn't borrow it. It is plain data, so it
+/// can be kept and passed between threads freely; every use checks it against the arena
+/// and slot generation, so once its entry is removed or replaced (or the treap rebuilt)
+/// it resolves to nothing rather than to whatever took the slot.
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+pub struct SyncHandle {
+    arena: usize,
+    id: Id,
+    generation: u32,
+}
+
+impl<K: PartialOrd,P: PartialOrd,V,A: Aggregate<V>,H: HeapPolicy> Treap<K,P,V,A,H> {
+    pub fn handle(&self, key: &K) -> Result<Option<SyncHandle>,Error> {
+        let mut node = self.root;
+        while let Some(id) = node {
+            let entry = self.index.get(&node).map_err(Error::Index)?;
+            if entry.key == *key {
+                let generation = self.index.generation(id).ok_or(Error::Index(IndexError::Empty(id)))?;
+                return Ok(Some(SyncHandle { arena: self.index.tag, id, generation }));
+            }
+            node = if entry.key > *key { entry.left } else { entry.right };
+        }
+        Ok(None)
+    }
+    /// Entry behind `handle`, or `None` if the handle is stale.
+    pub fn resolve(&self, handle: &SyncHandle) -> Option<(&K,&P,&V)> {
+        if handle.arena != self.index.tag || self.index.generation(handle.id) != Some(handle.generation) { return None; }
+        let entry = self.index.get(&Some(handle.id)).ok()?;
+        Some((&entry.key,&entry.priority,&entry.value))
+    }
+    /// Entry behind `handle` with none of `resolve`'s checks: one indexed load. Debug
+    /// builds still check.
+    ///
+    /// # Safety
+    /// `handle` must have come from this treap and its entry must still be in it, i.e.
+    /// `resolve` would return `Some`.
+    pub unsafe fn resolve_unchecked(&self, handle: &SyncHandle) -> (&K,&P,&V) {
+        debug_assert!(self.resolve(handle).is_some(),"stale handle {:?}",handle);
+        let entry = self.index.get_unchecked(handle.id);
+        (&entry.key,&entry.priority,&entry.value)
+    }
+    /// Entries in key order, each with its handle, so a scan can note entries to come back
+    /// to without searching for their keys again.
+    pub fn iter_with_handles(&self) -> HandleIter<'_,K,P,V,A> {
+        HandleIter { iter: self.iter(), index: &self.index }
+    }
+}
+impl<K,P,V,H> Treap<K,P,V,(),H> {
+    /// Mutable value behind `handle` in O(1), or `None` if the handle is stale. Plain treaps
+    /// only, like `get_mut`.
+    pub fn resolve_mut(&mut self, handle: &SyncHandle) -> Option<(&K,&P,&mut V)> {
+        if handle.arena != self.index.tag || self.index.generation(handle.id) != Some(handle.generation) { return None; }
+        let entry = self.index.get_mut(&Some(handle.id)).ok()?;
+        Some((&entry.key,&entry.priority,&mut entry.value))
+    }
+    /// `resolve_mut` without its checks.
+    ///
+    /// # Safety
+    /// As `resolve_unchecked`.
+    pub unsafe fn resolve_unchecked_mut(&mut self, handle: &SyncHandle) -> (&K,&P,&mut V) {
+        debug_assert!(handle.arena == self.index.tag && self.index.generation(handle.id) == Some(handle.generation),"stale handle {:?}",handle);
+        let entry = self.index.get_unchecked_mut(handle.id);
+        (&entry.key,&entry.priority,&mut entry.value)
+    }
+}
+
+pub struct HandleIter<'t,K,P,V,A = ()> {
+    iter: Iter<'t,K,P,V,A>,
+    index: &'t Index<K,P,V,A>,
+}
+impl<'t,K,P,V,A> Iterator for HandleIter<'t,K,P,V,A> {
+    type Item = (SyncHandle,(&'t K,&'t P,&'t V));
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (id,entry) = self.iter.next_node()?;
+        let generation = self.index.generation(id)?;
+        Some((SyncHandle { arena: self.index.tag, id, generation },(&entry.key,&entry.priority,&entry.value)))
+    }
+    fn size_hint(&self) -> (usize,Option<usize>) {
+        self.iter.size_hint()
+    }
+}
@@ -0,0 +1,238 @@
+use core::hash::Hash;
+use core::ops::RangeBounds;
+
+use crate::{Node,Index,NodePtr,Iter,Range,Error,IndexError,KeyHash,PrioritySource};
+
+const SEED: u64 = 0x5745_4947_4854; // fixed, so every run and platform gets the same shape
+
+/// Ordered map whose shape depends only on its set of keys: a treap whose priorities are a
+/// fixed hash of the key (`KeyHash`), so no priorities are supplied, the same keys give the
+/// same tree whatever order they came and went in, and `P` is plain data carried next to
+/// the value. A shape fixed by the keys alone can't be kept up in O(log n) by comparing
+/// them, hence `K: Hash` for `insert` and `remove`.
+#[derive(Debug)]
+pub struct HashTreap<K,P,V> {
+    root: NodePtr,
+    index: Index<K,P,V>,
+}
+impl<K: PartialOrd,P,V> Default for HashTreap<K,P,V> {
+    fn default() -> HashTreap<K,P,V> {
+        HashTreap::new()
+    }
+}
+impl<K: PartialOrd,P,V> HashTreap<K,P,V> {
+    pub fn new() -> HashTreap<K,P,V> {
+        HashTreap { root: None, index: Index::new() }
+    }
+    pub fn len(&self) -> usize {
+        self.index.size()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+    pub fn iter(&self) -> Iter<'_,K,P,V> {
+        Iter::new(&self.index,self.root,self.len())
+    }
+    pub fn range<B: RangeBounds<K>>(&self, range: B) -> Range<'_,K,P,V> {
+        Range::new(&self.index,self.root,self.len(),range)
+    }
+    pub fn get(&self, key: &K) -> Result<Option<(&P,&V)>,Error> {
+        let node = find_node(&self.index,self.root,key).map_err(Error::Index)?;
+        if node.is_none() { return Ok(None); }
+        let entry = self.index.get(&node).map_err(Error::Index)?;
+        Ok(Some((&entry.priority,&entry.value)))
+    }
+    pub fn get_mut(&mut self, key: &K) -> Result<Option<(&P,&mut V)>,Error> {
+        let node = find_node(&self.index,self.root,key).map_err(Error::Index)?;
+        if node.is_none() { return Ok(None); }
+        let entry = self.index.get_mut(&node).map_err(Error::Index)?;
+        Ok(Some((&entry.priority,&mut entry.value)))
+    }
+    pub fn depth(&self) -> Result<usize,Error> {
+        fn depth_node<K,P,V>(index: &Index<K,P,V>, node: NodePtr) -> Result<usize,IndexError> {
+            if node.is_none() { return Ok(0); }
+            let entry = index.get(&node)?;
+            Ok(1 + usize::max(depth_node(index,entry.left)?,depth_node(index,entry.right)?))
+        }
+
+        depth_node(&self.index,self.root).map_err(Error::Index)
+    }
+}
+
+impl<K: PartialOrd + Hash,P,V> HashTreap<K,P,V> {
+    pub fn insert(&mut self, key: K, priority: P, value: V) -> Result<Option<(P,V)>,Error> {
+        let (root,old) = insert_node(&mut self.index,self.root,key,priority,value).map_err(Error::Index)?;
+        self.root = root;
+        Ok(old)
+    }
+    pub fn remove(&mut self, key: &K) -> Result<Option<(P,V)>,Error> {
+        let (root,removed) = remove_node(&mut self.index,self.root,key).map_err(Error::Index)?;
+        self.root = root;
+        Ok(removed)
+    }
+}
+
+impl<'t,K: PartialOrd,P,V> IntoIterator for &'t HashTreap<K,P,V> {
+    type Item = (&'t K, &'t P, &'t V);
+    type IntoIter = Iter<'t,K,P,V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+fn children<K,P,V>(index: &Index<K,P,V>, node: NodePtr) -> Result<(NodePtr,NodePtr),IndexError> {
+    let entry = index.get(&node)?;
+    Ok((entry.left,entry.right))
+}
+fn rank<K: Hash>(key: &K) -> u64 {
+    KeyHash::new(SEED).priority(key)
+}
+// `a` above `b`: by key hash, ties to the larger key
+fn outranks<K: PartialOrd + Hash,P,V>(index: &Index<K,P,V>, a: NodePtr, b: NodePtr) -> Result<bool,IndexError> {
+    let (a,b) = (&index.get(&a)?.key,&index.get(&b)?.key);
+    Ok(match rank(a).cmp(&rank(b)) {
+        core::cmp::Ordering::Equal => a > b,
+        order => order == core::cmp::Ordering::Greater,
+    })
+}
+fn rotate_left<K,P,V>(index: &mut Index<K,P,V>, node: NodePtr) -> Result<NodePtr,IndexError> {
+    let (_,r) = children(index,node)?;
+    let (rl,_) = children(index,r)?;
+    index.get_mut(&node)?.right = rl;
+    index.get_mut(&r)?.left = node;
+    Ok(r)
+}
+fn rotate_right<K,P,V>(index: &mut Index<K,P,V>, node: NodePtr) -> Result<NodePtr,IndexError> {
+    let (l,_) = children(index,node)?;
+    let (_,lr) = children(index,l)?;
+    index.get_mut(&node)?.left = lr;
+    index.get_mut(&l)?.right = node;
+    Ok(l)
+}
+fn find_node<K: PartialOrd,P,V>(index: &Index<K,P,V>, node: NodePtr, key: &K) -> Result<NodePtr,IndexError> {
+    let mut node = node;
+    while node.is_some() {
+        let entry = index.get(&node)?;
+        if entry.key == *key { break; }
+        node = if entry.key > *key { entry.left } else { entry.right };
+    }
+    Ok(node)
+}
+// added as a leaf, then rotated up past every node it outranks
+fn insert_node<K: PartialOrd + Hash,P,V>(index: &mut Index<K,P,V>, node: NodePtr, key: K, priority: P, value: V) -> Result<(NodePtr,Option<(P,V)>),IndexError> {
+    if node.is_none() {
+        let new = index.insert(Node::new(key,priority,value));
+        return Ok((new,None));
+    }
+    let entry = index.get_mut(&node)?;
+    if entry.key == key {
+        let priority = core::mem::replace(&mut entry.priority,priority);
+        let value = core::mem::replace(&mut entry.value,value);
+        return Ok((node,Some((priority,value))));
+    }
+    let (go_left,l,r) = (entry.key > key,entry.left,entry.right);
+    let (child,old) = if go_left {
+        let (l,old) = insert_node(index,l,key,priority,value)?;
+        index.get_mut(&node)?.left = l;
+        (l,old)
+    } else {
+        let (r,old) = insert_node(index,r,key,priority,value)?;
+        index.get_mut(&node)?.right = r;
+        (r,old)
+    };
+    if old.is_some() { return Ok((node,old)); }
+    if !outranks(index,child,node)? { return Ok((node,None)); }
+    let top = if go_left { rotate_right(index,node)? } else { rotate_left(index,node)? };
+    Ok((top,None))
+}
+fn remove_node<K: PartialOrd + Hash,P,V>(index: &mut Index<K,P,V>, node: NodePtr, key: &K) -> Result<(NodePtr,Option<(P,V)>),IndexError> {
+    if node.is_none() { return Ok((None,None)); }
+    let entry = index.get(&node)?;
+    if entry.key == *key {
+        let removed = index.remove(&node)?;
+        let root = merge(index,removed.left,removed.right)?;
+        return Ok((root,Some((removed.priority,removed.value))));
+    }
+    let removed = if entry.key > *key {
+        let (l,removed) = remove_node(index,entry.left,key)?;
+        index.get_mut(&node)?.left = l;
+        removed
+    } else {
+        let (r,removed) = remove_node(index,entry.right,key)?;
+        index.get_mut(&node)?.right = r;
+        removed
+    };
+    Ok((node,removed))
+}
+// joins the two subtrees of a removed node, the higher ranked root on top
+fn merge<K: PartialOrd + Hash,P,V>(index: &mut Index<K,P,V>, left: NodePtr, right: NodePtr) -> Result<NodePtr,IndexError> {
+    if left.is_none() { return Ok(right); }
+    if right.is_none() { return Ok(left); }
+    if outranks(index,left,right)? {
+        let (_,lr) = children(index,left)?;
+        let merged = merge(index,lr,right)?;
+        index.get_mut(&left)?.right = merged;
+        Ok(left)
+    } else {
+        let (rl,_) = children(index,right)?;
+        let merged = merge(index,left,rl)?;
+        index.get_mut(&right)?.left = merged;
+        Ok(right)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use std::collections::BTreeMap;
+    use std::vec::Vec;
+    use super::*;
+
+    // keys in preorder, which pins down the tree
+    fn shape(t: &HashTreap<u32,(),u32>) -> Vec<u32> {
+        let (mut out,mut stack) = (Vec::new(),std::vec![t.root]);
+        while let Some(node) = stack.pop() {
+            if node.is_none() { continue; }
+            let entry = t.index.get(&node).unwrap();
+            out.push(entry.key);
+            stack.push(entry.right);
+            stack.push(entry.left);
+        }
+        out
+    }
+
+    #[test]
+    fn shape_depends_only_on_keys() {
+        let mut state = 1u64;
+        let mut next = |modulo: u64| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % modulo) as u32
+        };
+        let mut expected = None;
+        for _ in 0 .. 20 {
+            let (mut t,mut model) = (HashTreap::new(),BTreeMap::new());
+            for _ in 0 .. 2000 {
+                let key = next(300);
+                if next(3) == 0 {
+                    assert_eq!(t.remove(&key).unwrap().map(|(_,v)| v),model.remove(&key));
+                } else {
+                    assert_eq!(t.insert(key,(),key).unwrap().map(|(_,v)| v),model.insert(key,key));
+                }
+            }
+            // bring every run to the same key set
+            for key in 0 .. 300 {
+                match key % 2 {
+                    0 => { t.insert(key,(),key).unwrap(); },
+                    _ => { t.remove(&key).unwrap(); },
+                }
+            }
+            assert!(t.iter().map(|(k,_,_)| *k).eq((0 .. 300).step_by(2)));
+            let shape = shape(&t);
+            assert_eq!(shape.len(),150);
+            assert_eq!(*expected.get_or_insert_with(|| shape.clone()),shape);
+        }
+    }
+}
@@ -0,0 +1,310 @@
+use core::ops::{Bound,RangeBounds};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use rand::{RngCore,SeedableRng};
+use rand::rngs::StdRng;
+
+use crate::Error;
+#[cfg(feature = "std")]
+use crate::priority::entropy_rng;
+
+// Nodes are boxed rather than kept in an arena: a half split off owns its nodes outright,
+// so `split_at` and `concat` only relink the O(log n) nodes along the cut.
+type Link<V> = Option<Box<Node<V>>>;
+
+#[derive(Debug)]
+struct Node<V> {
+    value: V,
+    priority: u64,
+    size: usize,
+    flipped: bool, // children are to be swapped and flipped; pushed down lazily
+    left: Link<V>,
+    right: Link<V>,
+}
+
+impl<V> Node<V> {
+    fn children(&self, flip: bool) -> (&Link<V>,&Link<V>) {
+        match flip {
+            false => (&self.left,&self.right),
+            true => (&self.right,&self.left),
+        }
+    }
+    fn update(&mut self) {
+        self.size = 1 + size(&self.left) + size(&self.right);
+    }
+    fn push(&mut self) {
+        if !self.flipped { return; }
+        self.flipped = false;
+        core::mem::swap(&mut self.left,&mut self.right);
+        for child in self.left.iter_mut().chain(self.right.iter_mut()) {
+            child.flipped = !child.flipped;
+        }
+    }
+}
+
+/// Sequence stored as a treap whose key is the implicit position, kept through subtree sizes.
+#[derive(Debug)]
+pub struct ImplicitTreap<V,R = StdRng> {
+    root: Link<V>,
+    rng: R,
+}
+#[cfg(feature = "std")]
+impl<V> Default for ImplicitTreap<V> {
+    fn default() -> ImplicitTreap<V> {
+        ImplicitTreap::new()
+    }
+}
+#[cfg(feature = "std")]
+impl<V> ImplicitTreap<V> {
+    pub fn new() -> ImplicitTreap<V> {
+        ImplicitTreap::with_rng(entropy_rng())
+    }
+}
+impl<V,R: SeedableRng + RngCore> ImplicitTreap<V,R> {
+    pub fn with_seed(seed: u64) -> ImplicitTreap<V,R> {
+        ImplicitTreap::with_rng(R::seed_from_u64(seed))
+    }
+    /// Splits into `[0,i)` and `[i,len)` in O(log n); the second half gets an RNG seeded
+    /// from this one.
+    pub fn split_at(mut self, i: usize) -> Result<(Self,Self),Error> {
+        if i > self.len() { return Err(Error::OutOfRange(i)); }
+        let rng = R::seed_from_u64(self.rng.next_u64());
+        let (l,r) = split(self.root.take(),i);
+        Ok((ImplicitTreap { root: l, rng: self.rng }, ImplicitTreap { root: r, rng }))
+    }
+}
+impl<V,R: RngCore> ImplicitTreap<V,R> {
+    pub fn with_rng(rng: R) -> ImplicitTreap<V,R> {
+        ImplicitTreap { root: None, rng }
+    }
+    pub fn len(&self) -> usize {
+        size(&self.root)
+    }
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+    pub fn get(&self, i: usize) -> Result<Option<&V>,Error> {
+        let mut node = &self.root;
+        let mut flip = false;
+        let mut i = i;
+        while let Some(entry) = node {
+            flip ^= entry.flipped;
+            let (l,r) = entry.children(flip);
+            let ls = size(l);
+            node = match i {
+                i if i < ls => l,
+                i if i == ls => return Ok(Some(&entry.value)),
+                _ => {
+                    i -= ls + 1;
+                    r
+                },
+            };
+        }
+        Ok(None)
+    }
+    pub fn get_mut(&mut self, i: usize) -> Result<Option<&mut V>,Error> {
+        let mut node = self.root.as_deref_mut();
+        let mut flip = false;
+        let mut i = i;
+        while let Some(entry) = node {
+            flip ^= entry.flipped;
+            let ls = size(entry.children(flip).0);
+            if i == ls { return Ok(Some(&mut entry.value)); }
+            let left = i < ls;
+            if !left { i -= ls + 1; }
+            node = match left != flip {
+                true => entry.left.as_deref_mut(),
+                false => entry.right.as_deref_mut(),
+            };
+        }
+        Ok(None)
+    }
+    pub fn insert_at(&mut self, i: usize, value: V) -> Result<(),Error> {
+        if i > self.len() { return Err(Error::OutOfRange(i)); }
+        let priority = self.rng.next_u64();
+        let new = Some(Box::new(Node { value, priority, size: 1, flipped: false, left: None, right: None }));
+        let (l,r) = split(self.root.take(),i);
+        self.root = merge(merge(l,new),r);
+        Ok(())
+    }
+    pub fn remove_at(&mut self, i: usize) -> Result<Option<V>,Error> {
+        if i >= self.len() { return Ok(None); }
+        let (l,r) = split(self.root.take(),i);
+        let (mid,r) = split(r,1);
+        self.root = merge(l,r);
+        Ok(mid.map(|node| node.value))
+    }
+    pub fn push_back(&mut self, value: V) -> Result<(),Error> {
+        self.insert_at(self.len(),value)
+    }
+    pub fn push_front(&mut self, value: V) -> Result<(),Error> {
+        self.insert_at(0,value)
+    }
+    pub fn pop_back(&mut self) -> Result<Option<V>,Error> {
+        match self.len() {
+            0 => Ok(None),
+            n => self.remove_at(n - 1),
+        }
+    }
+    pub fn pop_front(&mut self) -> Result<Option<V>,Error> {
+        self.remove_at(0)
+    }
+    /// Appends `other` after `self` in O(log n), keeping this one's RNG.
+    pub fn concat(mut self, mut other: ImplicitTreap<V,R>) -> Result<ImplicitTreap<V,R>,Error> {
+        self.root = merge(self.root.take(),other.root.take());
+        Ok(self)
+    }
+    pub fn iter(&self) -> Iter<'_,V> {
+        let mut iter = Iter { stack: Vec::new(), remaining: self.len() };
+        iter.push_left(&self.root,false);
+        iter
+    }
+    pub fn slice<B: RangeBounds<usize>>(&self, range: B) -> Result<Iter<'_,V>,Error> {
+        let (start,end) = bounds(range,self.len())?;
+        let mut iter = Iter { stack: Vec::new(), remaining: end - start };
+        let mut node = &self.root;
+        let mut flip = false;
+        let mut i = start;
+        while let (Some(entry),true) = (node,iter.remaining > 0) {
+            flip ^= entry.flipped;
+            let (l,r) = entry.children(flip);
+            let ls = size(l);
+            if i <= ls { iter.stack.push((entry,flip)); }
+            node = match i {
+                i if i < ls => l,
+                i if i == ls => &None,
+                _ => {
+                    i -= ls + 1;
+                    r
+                },
+            };
+        }
+        Ok(iter)
+    }
+    /// Reverses the elements in `range` in O(log n); the reversal is applied lazily.
+    pub fn reverse_range<B: RangeBounds<usize>>(&mut self, range: B) -> Result<(),Error> {
+        let (start,end) = bounds(range,self.len())?;
+        let (l,r) = split(self.root.take(),start);
+        let (mut mid,r) = split(r,end - start);
+        if let Some(mid) = &mut mid { mid.flipped = !mid.flipped; }
+        self.root = merge(merge(l,mid),r);
+        Ok(())
+    }
+    /// Cuts out `range` and pastes it back at `dest`, counted in the sequence without the range.
+    pub fn move_range<B: RangeBounds<usize>>(&mut self, range: B, dest: usize) -> Result<(),Error> {
+        let (start,end) = bounds(range,self.len())?;
+        if dest > self.len() - (end - start) { return Err(Error::OutOfRange(dest)); }
+        let (l,r) = split(self.root.take(),start);
+        let (mid,r) = split(r,end - start);
+        let (l,r) = split(merge(l,r),dest);
+        self.root = merge(merge(l,mid),r);
+        Ok(())
+    }
+    /// Shifts left by `k`: the first `k` elements move to the end.
+    pub fn rotate_left(&mut self, k: usize) -> Result<(),Error> {
+        if k > self.len() { return Err(Error::OutOfRange(k)); }
+        let (l,r) = split(self.root.take(),k);
+        self.root = merge(r,l);
+        Ok(())
+    }
+    /// Shifts right by `k`: the last `k` elements move to the front.
+    pub fn rotate_right(&mut self, k: usize) -> Result<(),Error> {
+        if k > self.len() { return Err(Error::OutOfRange(k)); }
+        self.rotate_left(self.len() - k)
+    }
+}
+
+impl<'t,V,R: RngCore> IntoIterator for &'t ImplicitTreap<V,R> {
+    type Item = &'t V;
+    type IntoIter = Iter<'t,V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct Iter<'t,V> {
+    stack: Vec<(&'t Node<V>,bool)>, // node, flip parity of its subtree
+    remaining: usize,
+}
+impl<'t,V> Iter<'t,V> {
+    fn push_left(&mut self, mut node: &'t Link<V>, mut flip: bool) {
+        while let Some(entry) = node {
+            flip ^= entry.flipped;
+            self.stack.push((entry,flip));
+            node = entry.children(flip).0;
+        }
+    }
+}
+impl<'t,V> Iterator for Iter<'t,V> {
+    type Item = &'t V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 { return None; }
+        let (entry,flip) = self.stack.pop()?;
+        self.push_left(entry.children(flip).1,flip);
+        self.remaining -= 1;
+        Some(&entry.value)
+    }
+    fn size_hint(&self) -> (usize,Option<usize>) {
+        (self.remaining,Some(self.remaining))
+    }
+}
+
+pub(crate) fn bounds<B: RangeBounds<usize>>(range: B, len: usize) -> Result<(usize,usize),Error> {
+    let start = match range.start_bound() {
+        Bound::Included(a) => *a,
+        Bound::Excluded(a) => a.checked_add(1).ok_or(Error::OutOfRange(*a))?,
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(b) => b.checked_add(1).ok_or(Error::OutOfRange(*b))?,
+        Bound::Excluded(b) => *b,
+        Bound::Unbounded => len,
+    };
+    if end > len { return Err(Error::OutOfRange(end)); }
+    if start > end { return Err(Error::OutOfRange(start)); }
+    Ok((start,end))
+}
+fn size<V>(node: &Link<V>) -> usize {
+    node.as_ref().map_or(0,|node| node.size)
+}
+fn split<V>(link: Link<V>, n: usize) -> (Link<V>,Link<V>) { // first n go left
+    let mut node = match link {
+        Some(node) => node,
+        None => return (None,None),
+    };
+    node.push();
+    let ls = size(&node.left);
+    if n <= ls {
+        let (a,b) = split(node.left.take(),n);
+        node.left = b;
+        node.update();
+        (a,Some(node))
+    } else {
+        let (a,b) = split(node.right.take(),n - ls - 1);
+        node.right = a;
+        node.update();
+        (Some(node),b)
+    }
+}
+fn merge<V>(left: Link<V>, right: Link<V>) -> Link<V> {
+    match (left,right) {
+        (None,right) => right,
+        (left,None) => left,
+        (Some(mut left),Some(mut right)) => {
+            if left.priority > right.priority {
+                left.push();
+                left.right = merge(left.right.take(),Some(right));
+                left.update();
+                Some(left)
+            } else {
+                right.push();
+                right.left = merge(Some(left),right.left.take());
+                right.update();
+                Some(right)
+            }
+        },
+    }
+}
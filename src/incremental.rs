@@ -0,0 +1,139 @@
+// Long operations split into steps of bounded work, for callers that must not block for
+// long, such as async tasks. Each `step(budget)` handles at most `budget` entries and
+// returns `true` once the operation is complete; the state object resumes where the
+// previous step stopped.
+
+use core::ops::RangeBounds;
+use core::cmp::Ordering;
+use core::iter::Peekable;
+use alloc::vec::Vec;
+
+use crate::{Treap,Aggregate,HeapPolicy,MaxPolicy,Error,IndexError};
+use crate::iter::{after_start,before_end};
+
+impl<K: PartialOrd,P: PartialOrd,V,A: Aggregate<V>,H: HeapPolicy> Treap<K,P,V,A,H> {
+    /// Builds a treap by inserting the entries of `entries` a few at a time.
+    pub fn build_incremental<I: IntoIterator<Item = (K,P,V)>>(entries: I) -> Build<K,P,V,A,I::IntoIter,H> {
+        Build { treap: Treap::new(), entries: entries.into_iter().peekable() }
+    }
+    /// Removes the entries with keys in `range`, a few at a time. The treap stays
+    /// consistent between steps.
+    pub fn remove_range_incremental<B: RangeBounds<K>>(&mut self, range: B) -> RemoveRange<'_,K,P,V,A,B,H> {
+        RemoveRange { treap: self, range, done: false }
+    }
+    /// `meld`, moving the entries of the smaller treap into the larger a few at a time.
+    pub fn meld_incremental(self, other: Treap<K,P,V,A,H>) -> Meld<K,P,V,A,H> {
+        let (target,source,swapped) = match self.len() >= other.len() {
+            true => (self,other,false),
+            false => (other,self,true),
+        };
+        Meld { target, source, swapped }
+    }
+}
+
+pub struct Build<K,P,V,A,I: Iterator,H = MaxPolicy> {
+    treap: Treap<K,P,V,A,H>,
+    entries: Peekable<I>,
+}
+impl<K: PartialOrd,P: PartialOrd,V,A: Aggregate<V>,I: Iterator<Item = (K,P,V)>,H: HeapPolicy> Build<K,P,V,A,I,H> {
+    pub fn step(&mut self, budget: usize) -> Result<bool,Error> {
+        for _ in 0 .. budget {
+            match self.entries.next() {
+                Some((key,priority,value)) => { self.treap.insert(key,priority,value)?; },
+                None => break,
+            }
+        }
+        Ok(self.entries.peek().is_none())
+    }
+    /// The treap built so far, finishing the remaining steps first.
+    pub fn finish(mut self) -> Result<Treap<K,P,V,A,H>,Error> {
+        while !self.step(usize::MAX)? {}
+        Ok(self.treap)
+    }
+}
+
+pub struct RemoveRange<'t,K,P,V,A,B,H = MaxPolicy> {
+    treap: &'t mut Treap<K,P,V,A,H>,
+    range: B,
+    done: bool,
+}
+impl<'t,K: PartialOrd,P: PartialOrd,V,A: Aggregate<V>,B: RangeBounds<K>,H: HeapPolicy> RemoveRange<'t,K,P,V,A,B,H> {
+    pub fn step(&mut self, budget: usize) -> Result<bool,Error> {
+        for _ in 0 .. budget {
+            if self.done { break; }
+            self.done = !self.remove_first().map_err(Error::Index)?;
+        }
+        Ok(self.done)
+    }
+    pub fn finish(mut self) -> Result<(),Error> {
+        while !self.step(usize::MAX)? {}
+        Ok(())
+    }
+    // drops the smallest entry in the range, if any, in O(log n)
+    fn remove_first(&mut self) -> Result<bool,IndexError> {
+        let index = &mut self.treap.index;
+        let mut path = Vec::new();
+        let mut first = None; // position in `path`
+        let mut node = self.treap.root;
+        while node.is_some() {
+            let entry = index.get(&node)?;
+            path.push(node);
+            node = if after_start(&entry.key,self.range.start_bound()) {
+                first = Some(path.len() - 1);
+                entry.left
+            } else {
+                entry.right
+            };
+        }
+        let first = match first {
+            Some(first) => first,
+            None => return Ok(false),
+        };
+        path.truncate(first + 1);
+        let node = path.pop().unwrap_or(None);
+        if !before_end(&index.get(&node)?.key,self.range.end_bound()) { return Ok(false); }
+        let removed = index.remove(&node)?;
+        let rest = Treap::<K,P,V,A,H>::merge_nodes(index,removed.left,removed.right)?;
+        match path.last() {
+            None => self.treap.root = rest,
+            Some(parent) => {
+                let parent = index.get_mut(parent)?;
+                if parent.left == node { parent.left = rest; } else { parent.right = rest; }
+            },
+        }
+        while let Some(above) = path.pop() {
+            Treap::update(index,above)?;
+        }
+        Ok(true)
+    }
+}
+
+pub struct Meld<K,P,V,A,H = MaxPolicy> {
+    target: Treap<K,P,V,A,H>,
+    source: Treap<K,P,V,A,H>,
+    swapped: bool, // `target` is the second treap, which loses exact ties
+}
+impl<K: PartialOrd,P: PartialOrd,V,A: Aggregate<V>,H: HeapPolicy> Meld<K,P,V,A,H> {
+    pub fn step(&mut self, budget: usize) -> Result<bool,Error> {
+        for _ in 0 .. budget {
+            let (key,priority,value) = match self.source.pop()? {
+                Some(entry) => entry,
+                None => break,
+            };
+            let keep = match self.target.priority(&key)? { // as in `meld`, the higher ranked stays
+                Some(existing) => match H::compare(existing,&priority) {
+                    Some(Ordering::Greater) => true,
+                    Some(Ordering::Equal) => !self.swapped,
+                    _ => false,
+                },
+                None => false,
+            };
+            if !keep { self.target.insert(key,priority,value)?; }
+        }
+        Ok(self.source.is_empty())
+    }
+    pub fn finish(mut self) -> Result<Treap<K,P,V,A,H>,Error> {
+        while !self.step(usize::MAX)? {}
+        Ok(self.target)
+    }
+}
@@ -0,0 +1,135 @@
+use core::ops::{Bound,RangeBounds};
+use alloc::vec::Vec;
+
+use crate::{Id,NodePtr,Index,Node};
+
+pub(crate) fn after_start<K: PartialOrd>(key: &K, bound: Bound<&K>) -> bool {
+    match bound {
+        Bound::Included(start) => *key >= *start,
+        Bound::Excluded(start) => *key > *start,
+        Bound::Unbounded => true,
+    }
+}
+
+pub(crate) fn before_end<K: PartialOrd>(key: &K, bound: Bound<&K>) -> bool {
+    match bound {
+        Bound::Included(end) => *key <= *end,
+        Bound::Excluded(end) => *key < *end,
+        Bound::Unbounded => true,
+    }
+}
+
+type Visit<'t,K,P,V,A> = (Id,&'t Node<K,P,V,A>);
+
+pub struct Iter<'t,K,P,V,A = ()> {
+    index: &'t Index<K,P,V,A>,
+    stack: Vec<Id>,
+    remaining: usize,
+}
+impl<'t,K,P,V,A> Iter<'t,K,P,V,A> {
+    pub(crate) fn new(index: &'t Index<K,P,V,A>, root: NodePtr, len: usize) -> Iter<'t,K,P,V,A> {
+        let mut iter = Iter { index, stack: Vec::new(), remaining: len };
+        iter.push_left(root);
+        iter
+    }
+    fn push_left(&mut self, mut node: NodePtr) {
+        while let Some(id) = node {
+            self.stack.push(id);
+            node = match self.index.get(&node) {
+                Ok(entry) => entry.left,
+                Err(_) => None,
+            };
+        }
+    }
+    pub(crate) fn next_node(&mut self) -> Option<Visit<'t,K,P,V,A>> {
+        let id = self.stack.pop()?;
+        match self.index.get(&Some(id)) {
+            Ok(entry) => {
+                self.push_left(entry.right);
+                self.remaining = self.remaining.saturating_sub(1);
+                Some((id,entry))
+            },
+            Err(_) => {
+                self.stack.clear();
+                None
+            },
+        }
+    }
+}
+impl<'t,K,P,V,A> Iterator for Iter<'t,K,P,V,A> {
+    type Item = (&'t K, &'t P, &'t V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_node().map(|(_,entry)| (&entry.key,&entry.priority,&entry.value))
+    }
+    fn size_hint(&self) -> (usize,Option<usize>) {
+        match self.stack.is_empty() {
+            true => (0,Some(0)),
+            false => (self.remaining,Some(self.remaining)),
+        }
+    }
+}
+
+pub struct Range<'t,K,P,V,A = ()> {
+    iter: Iter<'t,K,P,V,A>,
+    last: NodePtr,
+}
+impl<'t,K: PartialOrd,P,V,A> Range<'t,K,P,V,A> {
+    pub(crate) fn new<R: RangeBounds<K>>(index: &'t Index<K,P,V,A>, root: NodePtr, len: usize, range: R) -> Range<'t,K,P,V,A> {
+        Range::between(index,root,len,|key| after_start(key,range.start_bound()),|key| before_end(key,range.end_bound()))
+    }
+    // keys for which both hold, given that they form one run in key order
+    pub(crate) fn between<S: Fn(&K) -> bool, E: Fn(&K) -> bool>(index: &'t Index<K,P,V,A>, root: NodePtr, len: usize, after_start: S, before_end: E) -> Range<'t,K,P,V,A> {
+        let mut iter = Iter { index, stack: Vec::new(), remaining: len };
+        let mut node = root;
+        while let Some(id) = node {
+            let entry = match index.get(&node) {
+                Ok(entry) => entry,
+                Err(_) => break,
+            };
+            if after_start(&entry.key) {
+                iter.stack.push(id);
+                node = entry.left;
+            } else {
+                node = entry.right;
+            }
+        }
+
+        let mut last = None;
+        let mut node = root;
+        while let Some(id) = node {
+            let entry = match index.get(&node) {
+                Ok(entry) => entry,
+                Err(_) => break,
+            };
+            if before_end(&entry.key) {
+                last = Some(id);
+                node = entry.right;
+            } else {
+                node = entry.left;
+            }
+        }
+
+        let empty = match (last,iter.stack.last()) {
+            (Some(_),Some(first)) => match index.get(&Some(*first)) {
+                Ok(entry) => !before_end(&entry.key),
+                Err(_) => true,
+            },
+            _ => true,
+        };
+        if empty { iter.stack.clear(); }
+        Range { iter, last }
+    }
+}
+impl<'t,K,P,V,A> Iterator for Range<'t,K,P,V,A> {
+    type Item = (&'t K, &'t P, &'t V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (id,entry) = self.iter.next_node()?;
+        if Some(id) == self.last { self.iter.stack.clear(); }
+        Some((&entry.key,&entry.priority,&entry.value))
+    }
+    fn size_hint(&self) -> (usize,Option<usize>) {
+        (0,self.iter.size_hint().1)
+    }
+}
@@ -1,5 +1,136 @@
-use std::collections::VecDeque;
-use std::cmp::Reverse;
+// Only `alloc` is needed; the `std` feature (on by default) adds the modules built on
+// locks, hash maps, system time and OS entropy.
+#![no_std]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+use alloc::collections::{VecDeque,BinaryHeap,TryReserveError};
+use core::ops::{Add,Bound,RangeBounds,ControlFlow};
+use core::iter::FromIterator;
+use core::cmp::Ordering;
+use core::marker::PhantomData;
+use core::fmt;
+use core::sync::atomic::{AtomicUsize,Ordering as AtomicOrdering};
+use alloc::vec;
+use alloc::vec::Vec;
+
+#[macro_use]
+mod macros;
+mod iter;
+mod priority;
+mod aggregate;
+mod policy;
+mod prefix;
+mod handle;
+mod entry;
+mod view;
+mod merged;
+mod repair;
+mod limits;
+#[cfg(feature = "access-stats")]
+mod stats;
+#[cfg(feature = "serde")]
+mod serial;
+pub mod map;
+pub mod set;
+pub mod queue;
+pub mod multimap;
+pub mod counter;
+pub mod expiring;
+pub mod cache;
+pub mod implicit;
+pub mod rope;
+pub mod window;
+pub mod hashed;
+pub mod adaptive;
+pub mod frozen;
+pub mod sequenced;
+#[cfg(feature = "std")]
+pub mod reverse;
+pub mod observed;
+#[cfg(target_has_atomic = "ptr")] // `Arc`
+pub mod persistent;
+#[cfg(target_has_atomic = "ptr")] // on `persistent`
+pub mod cow;
+#[cfg(feature = "std")]
+pub mod shared;
+#[cfg(feature = "std")]
+pub mod sharded;
+pub mod incremental;
+#[cfg(feature = "rayon")]
+pub mod par;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(any(feature = "quickcheck",feature = "proptest"))]
+pub mod testing;
+
+pub use iter::{Iter,Range};
+pub use priority::{PrioritySource,KeyHash};
+pub use aggregate::{Aggregate,MinMax,Sum,Count};
+pub use policy::{HeapPolicy,MaxPolicy,MinPolicy,ReverseTies,TotalOrder};
+pub use prefix::Prefix;
+pub use handle::{SyncHandle,HandleIter};
+pub use entry::{EntryRef,OccupiedEntryRef,VacantEntryRef};
+pub use view::{Subtree,RangeView};
+pub use merged::{Merged,Ties};
+pub use repair::RepairReport;
+pub use limits::{Limits,Limit};
+#[cfg(feature = "serde")]
+pub use serial::layout;
+#[cfg(feature = "access-stats")]
+pub use stats::AccessStats;
+pub use map::TreapMap;
+pub use set::TreapSet;
+pub use queue::TreapQueue;
+pub use multimap::TreapMultimap;
+pub use counter::TreapCounter;
+pub use expiring::{ExpiringTreap,Clock,ManualClock};
+#[cfg(feature = "std")]
+pub use expiring::SystemClock;
+pub use cache::{TreapCache,CacheStats};
+pub use implicit::ImplicitTreap;
+pub use rope::Rope;
+pub use window::SlidingWindow;
+pub use hashed::HashTreap;
+pub use adaptive::AdaptiveMap;
+pub use frozen::FrozenTreap;
+pub use sequenced::SequencedTreap;
+#[cfg(feature = "std")]
+pub use reverse::ReverseIndexedTreap;
+pub use observed::{ObservedTreap,Observer,Event};
+#[cfg(target_has_atomic = "ptr")]
+pub use persistent::{PersistentTreap,Change};
+#[cfg(target_has_atomic = "ptr")]
+pub use cow::{CowTreap,Checkpoint};
+#[cfg(feature = "std")]
+pub use shared::SharedTreap;
+#[cfg(feature = "std")]
+pub use sharded::ShardedTreapMap;
+
+// Thread-safety is part of the API: this stops compiling if a change loses it.
+const _: fn() = || {
+    fn send_sync<T: Send + Sync>() {}
+    send_sync::<Treap<u64,u64,u64>>();
+    send_sync::<Iter<'static,u64,u64,u64>>();
+    send_sync::<SyncHandle>();
+    send_sync::<TreapMap<u64,u64>>();
+    send_sync::<ImplicitTreap<u64>>();
+    send_sync::<Rope>();
+    #[cfg(target_has_atomic = "ptr")]
+    send_sync::<PersistentTreap<u64,u64,u64>>();
+    #[cfg(target_has_atomic = "ptr")]
+    send_sync::<CowTreap<u64,u64,u64>>();
+    #[cfg(feature = "std")]
+    send_sync::<SharedTreap<u64,u64,u64>>();
+    #[cfg(feature = "std")]
+    send_sync::<ShardedTreapMap<u64,u64>>();
+    send_sync::<SequencedTreap<u64,u64,u64>>();
+    #[cfg(feature = "std")]
+    send_sync::<ReverseIndexedTreap<u64,u64,u64>>();
+};
 
 type Id = usize;
 type NodePtr = Option<Id>;
@@ -7,6 +138,59 @@ type NodePtr = Option<Id>;
 #[derive(Debug)]
 pub enum Error {
     Index(IndexError),
+    OutOfRange(usize),
+    Unsorted(usize), // position of the first key not above its predecessor
+    Checkpoint(usize), // checkpoint already rolled back or released
+    KeyNotFound, // from the strict operations, for which a missing key is a bug
+    DuplicateKey, // key already present where it had to be new
+    CapacityExceeded(usize), // limit that the operation would have passed
+    LimitExceeded(Limit), // from `checked_insert`
+    Overflow, // a count past `usize::MAX`
+    Alloc(TryReserveError), // from the `try_` operations, left as they were
+    #[cfg(feature = "bincode")]
+    Stream(bincode::Error), // from `serialize_into` or `deserialize_from`
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Index(_) => f.write_str("corrupted treap"),
+            Error::OutOfRange(i) => write!(f,"position {} out of range",i),
+            Error::Unsorted(i) => write!(f,"key at {} not above the one before",i),
+            Error::Checkpoint(id) => write!(f,"checkpoint {} already rolled back or released",id),
+            Error::KeyNotFound => f.write_str("key not found"),
+            Error::DuplicateKey => f.write_str("key already present"),
+            Error::CapacityExceeded(limit) => write!(f,"limit of {} exceeded",limit),
+            Error::LimitExceeded(limit) => write!(f,"{} exceeded",limit),
+            Error::Overflow => f.write_str("count overflowed"),
+            Error::Alloc(_) => f.write_str("allocation failed"),
+            #[cfg(feature = "bincode")]
+            Error::Stream(_) => f.write_str("reading or writing the stream failed"),
+        }
+    }
+}
+#[cfg(feature = "std")]
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Index(e) => Some(e),
+            Error::Alloc(e) => Some(e),
+            #[cfg(feature = "bincode")]
+            Error::Stream(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// A key found more than once where keys must be unique; becomes `Error::DuplicateKey`,
+/// without the key, through `?`.
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct DuplicateKeyError<K> {
+    pub key: K,
+}
+impl<K> From<DuplicateKeyError<K>> for Error {
+    fn from(_: DuplicateKeyError<K>) -> Error {
+        Error::DuplicateKey
+    }
 }
 
 #[derive(Debug)]
@@ -14,26 +198,94 @@ pub enum IndexError {
     None,
     Empty(Id),
     OutOfBounds(Id),
+    Shared(Id), // reached again while in hand, through a shared or cyclic link
+}
+impl fmt::Display for IndexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IndexError::None => f.write_str("followed a missing link"),
+            IndexError::Empty(id) => write!(f,"node {} is free",id),
+            IndexError::OutOfBounds(id) => write!(f,"node {} is past the arena",id),
+            IndexError::Shared(id) => write!(f,"node {} reached twice",id),
+        }
+    }
+}
+#[cfg(feature = "std")]
+impl std::error::Error for IndexError {}
+
+static ARENAS: AtomicUsize = AtomicUsize::new(0);
+
+// Adds one, returning the value before. Pointer-sized, as many 32-bit targets have no
+// 64-bit atomics; those with no atomic add at all (thumbv6m) get a load and a store, which
+// an interrupt can come between: a count may then fall short or a tag repeat, weakening
+// only the stale handle checks and access stats they feed.
+fn bump(counter: &AtomicUsize) -> usize {
+    #[cfg(target_has_atomic = "ptr")]
+    return counter.fetch_add(1,AtomicOrdering::Relaxed);
+    #[cfg(not(target_has_atomic = "ptr"))]
+    {
+        let old = counter.load(AtomicOrdering::Relaxed);
+        counter.store(old.wrapping_add(1),AtomicOrdering::Relaxed);
+        old
+    }
 }
 
 #[derive(Debug)]
-struct DirectVecIndex<K,P,V> {
+struct DirectVecIndex<N> {
     reuse: Vec<usize>,
-    index: Vec<Option<Node<K,P,V>>>,
+    index: Vec<Option<N>>,
+    generations: Vec<u32>, // bumped when a slot is freed, so stale handles can be told apart
+    tag: usize, // distinguishes arenas for handles
 }
-impl<K,P,V> DirectVecIndex<K,P,V> {
-    fn new() -> DirectVecIndex<K,P,V> {
+impl<N> DirectVecIndex<N> {
+    fn new() -> DirectVecIndex<N> {
+        DirectVecIndex::from_nodes(Vec::new())
+    }
+    fn from_nodes(nodes: Vec<N>) -> DirectVecIndex<N> {
         DirectVecIndex {
             reuse: Vec::new(),
-            index: Vec::new(),
+            generations: vec![0; nodes.len()],
+            index: nodes.into_iter().map(Some).collect(),
+            tag: bump(&ARENAS),
+        }
+    }
+    fn try_from_nodes(nodes: Vec<N>) -> Result<DirectVecIndex<N>,TryReserveError> {
+        let mut index = Vec::new();
+        index.try_reserve_exact(nodes.len())?;
+        let mut generations = Vec::new();
+        generations.try_reserve_exact(nodes.len())?;
+        generations.resize(nodes.len(),0);
+        index.extend(nodes.into_iter().map(Some));
+        Ok(DirectVecIndex { reuse: Vec::new(), index, generations, tag: bump(&ARENAS) })
+    }
+    // room for `additional` more inserts, and for freeing every slot then in use, so
+    // neither those inserts nor later removals allocate
+    fn try_reserve(&mut self, additional: usize) -> Result<(),TryReserveError> {
+        let grow = additional.saturating_sub(self.reuse.len());
+        self.index.try_reserve(grow)?;
+        self.generations.try_reserve(grow)?;
+        self.reuse.try_reserve(self.index.len().saturating_add(grow) - self.reuse.len())
+    }
+    // room for one more slot if there's none, growing as `push` would but not past `cap`
+    fn reserve_within(&mut self, cap: usize) {
+        if self.reuse.is_empty() && self.index.len() == self.index.capacity() {
+            let grow = self.index.len().max(4).min(cap.saturating_sub(self.index.len())).max(1);
+            self.index.reserve_exact(grow);
+            self.generations.reserve_exact(grow);
+        }
+    }
+    fn generation(&self, id: Id) -> Option<u32> { // of an occupied slot
+        match self.index.get(id) {
+            Some(Some(_)) => self.generations.get(id).copied(),
+            _ => None,
         }
     }
     fn size(&self) -> usize {
         let i = self.index.len();
         let r = self.reuse.len();
-        if i > r { i - r } else { 0 }
+        i.saturating_sub(r)
     }
-    fn insert(&mut self, node: Node<K,P,V>) -> NodePtr {
+    fn insert(&mut self, node: N) -> NodePtr {
         Some(match self.reuse.pop() {
             Some(id) => {
                 self.index[id] = Some(node);
@@ -42,11 +294,12 @@ impl<K,P,V> DirectVecIndex<K,P,V> {
             None => {
                 let id = self.index.len();
                 self.index.push(Some(node));
+                self.generations.push(0);
                 id
             },
         })
     }
-    fn remove(&mut self, id: &NodePtr) -> Result<Node<K,P,V>,IndexError> {
+    fn remove(&mut self, id: &NodePtr) -> Result<N,IndexError> {
         match id {
             None => Err(IndexError::None),
             Some(id) if *id >= self.index.len() => Err(IndexError::OutOfBounds(*id)),
@@ -54,12 +307,13 @@ impl<K,P,V> DirectVecIndex<K,P,V> {
                 None => Err(IndexError::Empty(*id)),
                 Some(node) => {
                     self.reuse.push(*id);
+                    self.generations[*id] = self.generations[*id].wrapping_add(1);
                     Ok(node)
                 },
             },
         }
     }
-    fn get(&self, id: &NodePtr) -> Result<&Node<K,P,V>,IndexError> {
+    fn get(&self, id: &NodePtr) -> Result<&N,IndexError> {
         match id {
             None => Err(IndexError::None),
             Some(id) => match self.index.get(*id) {
@@ -69,7 +323,7 @@ impl<K,P,V> DirectVecIndex<K,P,V> {
             },
         }
     }
-    fn get_mut(&mut self, id: &NodePtr) -> Result<&mut Node<K,P,V>,IndexError> {
+    fn get_mut(&mut self, id: &NodePtr) -> Result<&mut N,IndexError> {
         match id {
             None => Err(IndexError::None),
             Some(id) => match self.index.get_mut(*id) {
@@ -79,71 +333,292 @@ impl<K,P,V> DirectVecIndex<K,P,V> {
             },
         }
     }
+    // SAFETY (both): the caller vouches that slot `id` is in bounds and occupied
+    unsafe fn get_unchecked(&self, id: Id) -> &N {
+        debug_assert!(matches!(self.index.get(id),Some(Some(_))),"unchecked access to free slot {}",id);
+        self.index.get_unchecked(id).as_ref().unwrap_unchecked()
+    }
+    unsafe fn get_unchecked_mut(&mut self, id: Id) -> &mut N {
+        debug_assert!(matches!(self.index.get(id),Some(Some(_))),"unchecked access to free slot {}",id);
+        self.index.get_unchecked_mut(id).as_mut().unwrap_unchecked()
+    }
+    fn take(&mut self, id: &NodePtr) -> Result<N,IndexError> { // detach without releasing the slot
+        match id {
+            None => Err(IndexError::None),
+            Some(id) => match self.index.get_mut(*id) {
+                None => Err(IndexError::OutOfBounds(*id)),
+                Some(slot) => slot.take().ok_or(IndexError::Empty(*id)),
+            },
+        }
+    }
+    fn restore(&mut self, id: &NodePtr, node: N) -> Result<(),IndexError> {
+        match id {
+            None => Err(IndexError::None),
+            Some(id) => match self.index.get_mut(*id) {
+                None => Err(IndexError::OutOfBounds(*id)),
+                Some(slot) => {
+                    *slot = Some(node);
+                    Ok(())
+                },
+            },
+        }
+    }
 }
 
-impl<'t,K,P,V> IntoIterator for &'t DirectVecIndex<K,P,V> {
-    type Item = (Id, &'t Option<Node<K,P,V>>);
-    type IntoIter = std::iter::Enumerate<std::slice::Iter<'t,Option<Node<K,P,V>>>>;
+impl<N> DirectVecIndex<N> {
+    fn map<M,F: FnMut(N) -> M>(self, mut f: F) -> DirectVecIndex<M> { // same ids, new arena
+        DirectVecIndex {
+            reuse: self.reuse,
+            index: self.index.into_iter().map(|slot| slot.map(&mut f)).collect(),
+            generations: self.generations,
+            tag: bump(&ARENAS),
+        }
+    }
+}
+
+impl<'t,N> IntoIterator for &'t DirectVecIndex<N> {
+    type Item = (Id, &'t Option<N>);
+    type IntoIter = core::iter::Enumerate<core::slice::Iter<'t,Option<N>>>;
     
     fn into_iter(self) -> Self::IntoIter {
         self.index.iter().enumerate()
     }
 }
 
-type Index<K,P,V> = DirectVecIndex<K,P,V>;
+trait Links {
+    fn links(&mut self) -> (&mut NodePtr,&mut NodePtr);
+}
+
+impl<N: Links> DirectVecIndex<N> {
+    fn append(&mut self, other: DirectVecIndex<N>) -> Result<Vec<NodePtr>,IndexError> { // old id -> new id
+        let ids: Vec<NodePtr> = other.index.into_iter().map(|slot| match slot {
+            Some(node) => self.insert(node),
+            None => None,
+        }).collect();
+        let remap = |ptr: NodePtr| ptr.and_then(|id| ids.get(id).copied().flatten());
+        for id in &ids {
+            if id.is_none() { continue; }
+            let (left,right) = self.get_mut(id)?.links();
+            *left = remap(*left);
+            *right = remap(*right);
+        }
+        Ok(ids)
+    }
+}
+
+type Index<K,P,V,A = ()> = DirectVecIndex<Node<K,P,V,A>>;
 
-#[derive(Debug,Clone,Copy)]
-struct Node<K,P,V> {
+#[derive(Debug,Clone)]
+struct Node<K,P,V,A = ()> {
     key: K,
     priority: P,
     value: V,
+    agg: A, // of the whole subtree
     left: NodePtr,
     right: NodePtr,
+    #[cfg(feature = "access-stats")]
+    stats: stats::Counters,
+}
+
+impl<K,P,V,A: Aggregate<V>> Node<K,P,V,A> {
+    fn new(key: K, priority: P, value: V) -> Node<K,P,V,A> {
+        let agg = A::from_value(&value);
+        Node {
+            key, priority, value, agg, left: None, right: None,
+            #[cfg(feature = "access-stats")]
+            stats: Default::default(),
+        }
+    }
+}
+impl<K,P,V,A> Node<K,P,V,A> {
+    fn into_entry(self) -> (K,P,V) {
+        (self.key,self.priority,self.value)
+    }
+}
+impl<K,P,V,A> Links for Node<K,P,V,A> {
+    fn links(&mut self) -> (&mut NodePtr,&mut NodePtr) {
+        (&mut self.left,&mut self.right)
+    }
 }
 
 #[derive(Debug)]
-struct Split<K,P,V> {
+struct Split<K,P,V,A> {
     left: NodePtr,
     entry: NodePtr,
     right: NodePtr,
-    index: Index<K,P,V>,
+    index: Index<K,P,V,A>,
 }
 
 #[derive(Debug)]
-pub struct Treap<K,P,V> {
+struct Join<K,P,V,A> {
+    left: NodePtr,
+    right: NodePtr,
+    index: Index<K,P,V,A>,
+}
+
+// `build_sorted` fed one entry at a time, for sources that can't be wrapped in an iterator
+struct SortedBuilder<K,P,V,A,H> {
+    nodes: Vec<Node<K,P,V,A>>,
+    spine: Vec<Id>, // right spine of the tree built so far
+    policy: PhantomData<H>,
+}
+impl<K: PartialOrd,P: PartialOrd,V,A: Aggregate<V>,H: HeapPolicy> SortedBuilder<K,P,V,A,H> {
+    fn with_capacity(capacity: usize) -> SortedBuilder<K,P,V,A,H> {
+        SortedBuilder { nodes: Vec::with_capacity(capacity), spine: Vec::new(), policy: PhantomData }
+    }
+    fn update(&mut self, id: Id) { // children are final
+        let node = &self.nodes[id];
+        let mut agg = A::from_value(&node.value);
+        if let Some(l) = node.left { agg = A::combine(&self.nodes[l].agg,&agg); }
+        if let Some(r) = node.right { agg = A::combine(&agg,&self.nodes[r].agg); }
+        self.nodes[id].agg = agg;
+    }
+    fn push(&mut self, key: K, priority: P, value: V) { // key above every one pushed so far
+        self.push_node(Node::new(key,priority,value));
+    }
+    fn push_node(&mut self, mut node: Node<K,P,V,A>) { // relinked; keeps its access stats
+        let id = self.nodes.len();
+        let mut last = None;
+        while let Some(&top) = self.spine.last() {
+            if H::rank((&self.nodes[top].key,&self.nodes[top].priority),(&node.key,&node.priority)) == Some(Ordering::Greater) { break; }
+            last = self.spine.pop();
+            self.update(top);
+        }
+        if let Some(&top) = self.spine.last() { self.nodes[top].right = Some(id); }
+        node.left = last;
+        node.right = None;
+        self.nodes.push(node);
+        self.spine.push(id);
+    }
+    fn try_push(&mut self, key: K, priority: P, value: V) -> Result<(),TryReserveError> {
+        self.nodes.try_reserve(1)?;
+        self.spine.try_reserve(1)?;
+        self.push(key,priority,value);
+        Ok(())
+    }
+    fn link(&mut self) -> NodePtr { // root, once the last entry is in
+        let root = self.spine.first().copied();
+        while let Some(top) = self.spine.pop() {
+            self.update(top);
+        }
+        root
+    }
+    fn finish(mut self) -> Treap<K,P,V,A,H> {
+        Treap { root: self.link(), index: DirectVecIndex::from_nodes(self.nodes), policy: PhantomData }
+    }
+    fn try_finish(mut self) -> Result<Treap<K,P,V,A,H>,TryReserveError> {
+        let root = self.link();
+        Ok(Treap { root, index: DirectVecIndex::try_from_nodes(self.nodes)?, policy: PhantomData })
+    }
+}
+
+// node on the frontier of a best-first search, ordered by rank
+struct Ranked<'t,K,P,H> {
+    key: &'t K,
+    priority: &'t P,
+    id: Id,
+    policy: PhantomData<H>,
+}
+impl<'t,K: PartialOrd,P: PartialOrd,H: HeapPolicy> Ord for Ranked<'t,K,P,H> {
+    fn cmp(&self, other: &Ranked<'t,K,P,H>) -> Ordering {
+        H::rank((self.key,self.priority),(other.key,other.priority)).unwrap_or(Ordering::Equal)
+    }
+}
+impl<'t,K: PartialOrd,P: PartialOrd,H: HeapPolicy> PartialOrd for Ranked<'t,K,P,H> {
+    fn partial_cmp(&self, other: &Ranked<'t,K,P,H>) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl<'t,K: PartialOrd,P: PartialOrd,H: HeapPolicy> PartialEq for Ranked<'t,K,P,H> {
+    fn eq(&self, other: &Ranked<'t,K,P,H>) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+impl<'t,K: PartialOrd,P: PartialOrd,H: HeapPolicy> Eq for Ranked<'t,K,P,H> {}
+
+/// Search tree on `K` and heap on `P` at once, its nodes in an arena.
+///
+/// Operations return `Result` rather than panic: an `Error::Index` means the tree is
+/// corrupted. Most of them recurse down the tree, so priorities that make it much deeper
+/// than random ones would, e.g. rising with the key, can exhaust the stack.
+#[derive(Debug)]
+pub struct Treap<K,P,V,A = (),H = MaxPolicy> {
     root: NodePtr,
-    index: Index<K,P,V>,
+    index: Index<K,P,V,A>,
+    policy: PhantomData<H>,
+}
+impl<K: PartialOrd + PartialEq,P: PartialOrd,V,A: Aggregate<V>,H: HeapPolicy> Default for Treap<K,P,V,A,H> {
+    fn default() -> Treap<K,P,V,A,H> {
+        Treap::new()
+    }
 }
-impl<K: PartialOrd + PartialEq,P: PartialOrd,V> Treap<K,P,V> {
-    pub fn new() -> Treap<K,P,V> {
-        Treap{ root: None, index: Index::new() }
+impl<K: PartialOrd + PartialEq,P: PartialOrd,V,A: Aggregate<V>,H: HeapPolicy> Treap<K,P,V,A,H> {
+    pub fn new() -> Treap<K,P,V,A,H> {
+        Treap{ root: None, index: Index::new(), policy: PhantomData }
     }
     pub fn len(&self) -> usize {
         self.index.size()
     }
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+    pub fn iter(&self) -> Iter<'_,K,P,V,A> {
+        Iter::new(&self.index,self.root,self.len())
+    }
+    pub fn range<R: RangeBounds<K>>(&self, range: R) -> Range<'_,K,P,V,A> {
+        Range::new(&self.index,self.root,self.len(),range)
+    }
+    /// Entries whose key starts with `prefix`, e.g. all `(user, time)` keys of one user.
+    pub fn range_prefix<Q>(&self, prefix: &Q) -> Range<'_,K,P,V,A> where K: Prefix<Q> {
+        let after_start = |key: &K| matches!(key.cmp_prefix(prefix),Some(Ordering::Equal) | Some(Ordering::Greater));
+        let before_end = |key: &K| matches!(key.cmp_prefix(prefix),Some(Ordering::Less) | Some(Ordering::Equal));
+        Range::between(&self.index,self.root,self.len(),after_start,before_end)
+    }
     pub fn insert(&mut self, key: K, priority: P, value: V) -> Result<Option<(P,V)>,Error> {
-        let mut tmp = Treap { root: None, index: Index::new() };
-        std::mem::swap(&mut tmp, self);
+        self.insert_entry(key,priority,value).map(|(_,old)| old)
+    }
+    // `insert` that also gives the new node
+    pub(crate) fn insert_entry(&mut self, key: K, priority: P, value: V) -> Result<(NodePtr,Option<(P,V)>),Error> {
+        let mut tmp = Treap::new();
+        core::mem::swap(&mut tmp, self);
         let spl = tmp.split(&key).map_err(Error::Index)?;
-        let new_node = Node { key: key, priority: priority, value: value, left: None, right: None };
+        let new_node = Node::new(key,priority,value);
         let mut index = spl.index;
         let left = spl.left;
         let right = spl.right;
         let node = index.remove(&spl.entry).ok();
 
         let new = index.insert(new_node);
-        let root = Treap::merge_nodes(&mut index,left,new).map_err(Error::Index)?;
+        let root = Self::merge_nodes(&mut index,left,new).map_err(Error::Index)?;
         *self = Treap {
-            root: Treap::merge_nodes(&mut index,root,right).map_err(Error::Index)?,
-            index: index,
+            root: Self::merge_nodes(&mut index,root,right).map_err(Error::Index)?,
+            index,
+            policy: PhantomData,
         };
         
-        Ok(node.map(|node| (node.priority,node.value)))
+        Ok((new,node.map(|node| (node.priority,node.value))))
+    }
+    /// `insert` that reports a failed allocation as `Error::Alloc`, with the treap
+    /// untouched, instead of aborting. Only growing the arena allocates, and room is made
+    /// for freeing the new slot too, so removals never allocate either.
+    pub fn try_insert(&mut self, key: K, priority: P, value: V) -> Result<Option<(P,V)>,Error> {
+        self.index.try_reserve(1).map_err(Error::Alloc)?;
+        self.insert(key,priority,value)
+    }
+    /// `try_insert` of every entry, with room for the lower size hint made up front; on
+    /// `Error::Alloc` the entries before the failing one are in.
+    pub fn try_extend<I: IntoIterator<Item = (K,P,V)>>(&mut self, entries: I) -> Result<(),Error> {
+        let entries = entries.into_iter();
+        self.index.try_reserve(entries.size_hint().0).map_err(Error::Alloc)?;
+        for (key,priority,value) in entries {
+            self.try_insert(key,priority,value)?;
+        }
+        Ok(())
     }
     pub fn remove(&mut self, key: &K) -> Result<Option<(P,V)>,Error> {
-        let mut tmp = Treap { root: None, index: Index::new() };
-        std::mem::swap(&mut tmp, self);
-        let spl = tmp.split(&key).map_err(Error::Index)?;
+        let mut tmp = Treap::new();
+        core::mem::swap(&mut tmp, self);
+        let spl = tmp.split(key).map_err(Error::Index)?;
 
         let mut index = spl.index;
         let left = spl.left;
@@ -151,17 +626,20 @@ impl<K: PartialOrd + PartialEq,P: PartialOrd,V> Treap<K,P,V> {
         let node = index.remove(&spl.entry).ok();
 
         *self = Treap {
-            root: Treap::merge_nodes(&mut index,left,right).map_err(Error::Index)?,
-            index: index,
+            root: Self::merge_nodes(&mut index,left,right).map_err(Error::Index)?,
+            index,
+            policy: PhantomData,
         };
         
         Ok(node.map(|node| (node.priority,node.value)))
     }
     pub fn get<'t>(&'t self, key: &K) -> Result<Option<(&'t P, &'t V)>,Error> {
-        fn search_node<'t,K: PartialOrd + PartialEq,P,V>(index: &'t Index<K,P,V>, node: NodePtr, key: &K) -> Result<Option<(&'t P, &'t V)>,IndexError> {
+        fn search_node<'t,K: PartialOrd + PartialEq,P,V,A>(index: &'t Index<K,P,V,A>, node: NodePtr, key: &K) -> Result<Option<(&'t P, &'t V)>,IndexError> {
             if node.is_none() { return Ok(None); }
             let entry = index.get(&node)?;
             if entry.key == *key {
+                #[cfg(feature = "access-stats")]
+                entry.stats.touch();
                 Ok(Some((&entry.priority,&entry.value)))
             } else {
                 if entry.key > *key {
@@ -174,40 +652,56 @@ impl<K: PartialOrd + PartialEq,P: PartialOrd,V> Treap<K,P,V> {
 
         search_node(&self.index,self.root,key).map_err(Error::Index)
     }
-    pub fn get_mut<'t>(&'t mut self, key: &K) -> Result<Option<(&'t P, &'t mut V)>,Error> {
-        enum Action {
-            Found(NodePtr),
-            Left(NodePtr),
-            Right(NodePtr),
+    /// `get` without the arena's bounds and occupancy checks on the way down, for
+    /// lookup-bound loops. Debug builds still check.
+    ///
+    /// # Safety
+    /// The treap must be intact, i.e. no method may have returned `Error::Index` on it;
+    /// the links of a corrupted treap are followed blindly.
+    pub unsafe fn get_unchecked(&self, key: &K) -> Option<(&P,&V)> {
+        let mut node = self.root;
+        while let Some(id) = node {
+            let entry = self.index.get_unchecked(id);
+            node = match entry.key.partial_cmp(key) {
+                Some(Ordering::Equal) => {
+                    #[cfg(feature = "access-stats")]
+                    entry.stats.touch();
+                    return Some((&entry.priority,&entry.value));
+                },
+                Some(Ordering::Greater) => entry.left,
+                _ => entry.right,
+            };
         }
-        fn search_node<'t,K: PartialOrd + PartialEq,P,V>(index: &'t mut Index<K,P,V>, node: NodePtr, key: &K) -> Result<Option<(&'t P, &'t mut V)>,IndexError> {
-            if node.is_none() { return Ok(None); }
-            let action = {
-                let entry = index.get_mut(&node)?;
-                if entry.key == *key {
-                    Action::Found(node)
-                } else {
-                    if entry.key > *key {
-                        Action::Left(entry.left)
-                    } else {
-                        Action::Right(entry.right)
-                    }
-                }               
+        None
+    }
+    /// Converts into a read-only snapshot laid out as sorted arrays.
+    pub fn freeze(mut self) -> Result<FrozenTreap<K,P,V,A,H>,Error> {
+        let mut nodes = Vec::with_capacity(self.len());
+        Treap::take_nodes(&mut self.index,self.root,&mut nodes).map_err(Error::Index)?;
+        Ok(FrozenTreap::new(nodes.into_iter().map(Node::into_entry).collect()))
+    }
+    /// Edits a value in place and refreshes the aggregates above it; `false` if `key` is absent.
+    pub fn update_value<F: FnOnce(&mut V)>(&mut self, key: &K, f: F) -> Result<bool,Error> {
+        let mut path = Vec::new();
+        let mut node = self.root;
+        while node.is_some() {
+            let entry = self.index.get(&node).map_err(Error::Index)?;
+            path.push(node);
+            node = match entry.key.partial_cmp(key) {
+                Some(Ordering::Equal) => break,
+                Some(Ordering::Greater) => entry.left,
+                _ => entry.right,
             };
-            match action {
-                Action::Found(node) => {
-                    let node_ref = index.get_mut(&node)?;
-                    Ok(Some((&node_ref.priority,&mut node_ref.value)))
-                },
-                Action::Left(left) => search_node(index,left,key),
-                Action::Right(right) => search_node(index,right,key),
-            }
         }
-
-        search_node(&mut self.index,self.root,key).map_err(Error::Index)
+        if node.is_none() { return Ok(false); }
+        f(&mut self.index.get_mut(&node).map_err(Error::Index)?.value);
+        while let Some(node) = path.pop() {
+            Treap::update(&mut self.index,node).map_err(Error::Index)?;
+        }
+        Ok(true)
     }
     pub fn priority<'t>(&'t self, key: &K) -> Result<Option<&'t P>,Error> {
-        fn search_node<'t,K: PartialOrd + PartialEq,P,V>(index: &'t Index<K,P,V>, node: NodePtr, key: &K) -> Result<Option<&'t P>,IndexError> {
+        fn search_node<'t,K: PartialOrd + PartialEq,P,V,A>(index: &'t Index<K,P,V,A>, node: NodePtr, key: &K) -> Result<Option<&'t P>,IndexError> {
             if node.is_none() { return Ok(None); }
             let entry = index.get(&node)?;
             if entry.key == *key {
@@ -224,37 +718,262 @@ impl<K: PartialOrd + PartialEq,P: PartialOrd,V> Treap<K,P,V> {
         search_node(&self.index,self.root,key).map_err(Error::Index)
     }
     pub fn prioritize(&mut self, key: &K, new_p: P) -> Result<Option<P>,Error> {
-        let mut tmp = Treap { root: None, index: Index::new() };
-        std::mem::swap(&mut tmp, self);
-        let spl = tmp.split(&key).map_err(Error::Index)?;
+        let mut tmp = Treap::new();
+        core::mem::swap(&mut tmp, self);
+        let spl = tmp.split(key).map_err(Error::Index)?;
         
         let mut index = spl.index;
         let left = spl.left;
         let right = spl.right;
         let (old_p,new) = match index.remove(&spl.entry).ok() {
-            Some(node) => {
-                let new_node = Node { key: node.key, priority: new_p, value: node.value, left: None, right: None };
-                (Some(node.priority),index.insert(new_node))
+            Some(mut node) => { // reused whole, so anything else it carries survives
+                let old_p = core::mem::replace(&mut node.priority,new_p);
+                node.left = None;
+                node.right = None;
+                node.agg = A::from_value(&node.value);
+                (Some(old_p),index.insert(node))
             },
             None => (None,None),
         };
 
-        let root = Treap::merge_nodes(&mut index,left,new).map_err(Error::Index)?;
+        let root = Self::merge_nodes(&mut index,left,new).map_err(Error::Index)?;
         *self = Treap {
-            root: Treap::merge_nodes(&mut index,root,right).map_err(Error::Index)?,
-            index: index,
+            root: Self::merge_nodes(&mut index,root,right).map_err(Error::Index)?,
+            index,
+            policy: PhantomData,
         };
         
         Ok(old_p)
     }
+    /// `insert` of a key that must be new: `Error::DuplicateKey`, with the treap untouched,
+    /// if it's already there.
+    pub fn insert_new(&mut self, key: K, priority: P, value: V) -> Result<(),Error> {
+        if self.get(&key)?.is_some() { return Err(Error::DuplicateKey); }
+        self.insert(key,priority,value).map(|_| ())
+    }
+    /// `remove` of a key that must be present: `Error::KeyNotFound` rather than `Ok(None)`.
+    pub fn remove_strict(&mut self, key: &K) -> Result<(P,V),Error> {
+        self.remove(key)?.ok_or(Error::KeyNotFound)
+    }
+    /// `prioritize` of a key that must be present; returns the old priority.
+    pub fn prioritize_strict(&mut self, key: &K, new_p: P) -> Result<P,Error> {
+        self.prioritize(key,new_p)?.ok_or(Error::KeyNotFound)
+    }
     pub fn pop(&mut self) -> Result<Option<(K,P,V)>,Error> {
         if self.root.is_none() { return Ok(None); }
         let node = self.index.remove(&self.root.take()).map_err(Error::Index)?;
-        self.root = Treap::merge_nodes(&mut self.index,node.left,node.right).map_err(Error::Index)?;
+        self.root = Self::merge_nodes(&mut self.index,node.left,node.right).map_err(Error::Index)?;
         Ok(Some((node.key,node.priority,node.value)))
     }
+    /// Removes the `k` top-ranked entries and returns them best first. They form the top
+    /// of the tree, which is cut off in one pass rather than `k` separate pops.
+    pub fn pop_k(&mut self, k: usize) -> Result<Vec<(K,P,V)>,Error> {
+        // `top`: (id, position in the result), sorted by id
+        fn strip_node<K: PartialOrd,P: PartialOrd,V,A: Aggregate<V>,H: HeapPolicy>(index: &mut Index<K,P,V,A>, node: NodePtr, top: &[(Id,usize)], popped: &mut [Option<(K,P,V)>]) -> Result<NodePtr,IndexError> {
+            let pos = match node.map(|id| top.binary_search_by_key(&id,|&(id,_)| id)) {
+                Some(Ok(i)) => top[i].1,
+                _ => return Ok(node),
+            };
+            let entry = index.remove(&node)?;
+            let l = strip_node::<K,P,V,A,H>(index,entry.left,top,popped)?;
+            let r = strip_node::<K,P,V,A,H>(index,entry.right,top,popped)?;
+            popped[pos] = Some((entry.key,entry.priority,entry.value));
+            Treap::<K,P,V,A,H>::merge_nodes(index,l,r)
+        }
+
+        let mut top: Vec<(Id,usize)> = self.top_nodes(k).map_err(Error::Index)?.into_iter().enumerate().map(|(i,id)| (id,i)).collect();
+        top.sort_unstable();
+        let mut popped: Vec<Option<(K,P,V)>> = top.iter().map(|_| None).collect();
+        self.root = strip_node::<K,P,V,A,H>(&mut self.index,self.root,&top,&mut popped).map_err(Error::Index)?;
+        Ok(popped.into_iter().flatten().collect())
+    }
+    pub fn peek(&self) -> Result<Option<(&K,&P,&V)>,Error> {
+        if self.root.is_none() { return Ok(None); }
+        let node = self.index.get(&self.root).map_err(Error::Index)?;
+        Ok(Some((&node.key,&node.priority,&node.value)))
+    }
+    /// Borrowed snapshot in key order.
+    pub fn to_vec(&self) -> Vec<(&K,&P,&V)> {
+        self.iter().collect()
+    }
+    /// Borrowed snapshot in `pop` order, top-ranked first.
+    pub fn to_vec_by_priority(&self) -> Vec<(&K,&P,&V)> {
+        let mut entries = self.to_vec();
+        entries.sort_by(|a,b| H::rank((b.0,b.1),(a.0,a.1)).unwrap_or(Ordering::Equal));
+        entries
+    }
+    /// Whether every key here is also in `other`, by one merge walk over both, O(n + m).
+    pub fn is_subset<Q: PartialOrd,W,B: Aggregate<W>,G: HeapPolicy>(&self, other: &Treap<K,Q,W,B,G>) -> bool {
+        if self.len() > other.len() { return false; }
+        let mut theirs = other.iter().map(|(k,_,_)| k).peekable();
+        self.iter().all(|(key,_,_)| {
+            while theirs.next_if(|k| *k < key).is_some() {}
+            theirs.next_if(|k| *k == key).is_some()
+        })
+    }
+    pub fn is_superset<Q: PartialOrd,W,B: Aggregate<W>,G: HeapPolicy>(&self, other: &Treap<K,Q,W,B,G>) -> bool {
+        other.is_subset(self)
+    }
+    /// Entries whose key is in both treaps, in key order, combined by `f`. One merge walk
+    /// over both, O(n + m).
+    pub fn join<'t,Q: PartialOrd,W,B: Aggregate<W>,G: HeapPolicy,T,F>(&'t self, other: &'t Treap<K,Q,W,B,G>, mut f: F) -> Vec<T>
+    where F: FnMut(&'t K,(&'t P,&'t V),(&'t Q,&'t W)) -> T {
+        let mut out = Vec::new();
+        self.merge_join(other,|key,a,b| if let (Some(a),Some(b)) = (a,b) { out.push(f(key,a,b)); });
+        out
+    }
+    /// `join` keeping every entry of `self`.
+    pub fn left_join<'t,Q: PartialOrd,W,B: Aggregate<W>,G: HeapPolicy,T,F>(&'t self, other: &'t Treap<K,Q,W,B,G>, mut f: F) -> Vec<T>
+    where F: FnMut(&'t K,(&'t P,&'t V),Option<(&'t Q,&'t W)>) -> T {
+        let mut out = Vec::new();
+        self.merge_join(other,|key,a,b| if let Some(a) = a { out.push(f(key,a,b)); });
+        out
+    }
+    /// `join` keeping every entry of either treap.
+    pub fn outer_join<'t,Q: PartialOrd,W,B: Aggregate<W>,G: HeapPolicy,T,F>(&'t self, other: &'t Treap<K,Q,W,B,G>, mut f: F) -> Vec<T>
+    where F: FnMut(&'t K,Option<(&'t P,&'t V)>,Option<(&'t Q,&'t W)>) -> T {
+        let mut out = Vec::new();
+        self.merge_join(other,|key,a,b| out.push(f(key,a,b)));
+        out
+    }
+    fn merge_join<'t,Q: PartialOrd,W,B: Aggregate<W>,G: HeapPolicy,F>(&'t self, other: &'t Treap<K,Q,W,B,G>, mut f: F)
+    where F: FnMut(&'t K,Option<(&'t P,&'t V)>,Option<(&'t Q,&'t W)>) {
+        let (mut a,mut b) = (self.iter().peekable(),other.iter().peekable());
+        loop {
+            let order = match (a.peek(),b.peek()) {
+                (None,None) => return,
+                (Some(_),None) => Ordering::Less,
+                (None,Some(_)) => Ordering::Greater,
+                (Some(x),Some(y)) => x.0.partial_cmp(y.0).unwrap_or(Ordering::Equal),
+            };
+            let x = a.next_if(|_| order != Ordering::Greater);
+            let y = b.next_if(|_| order != Ordering::Less);
+            match (x,y) {
+                (Some((key,p,v)),y) => f(key,Some((p,v)),y.map(|(_,q,w)| (q,w))),
+                (None,Some((key,q,w))) => f(key,None,Some((q,w))),
+                (None,None) => return,
+            }
+        }
+    }
+    /// Calls `f` on the entries in key order until it breaks, without setting up an iterator.
+    pub fn for_each_in_order<B,F: FnMut(&K,&P,&V) -> ControlFlow<B>>(&self, f: F) -> Result<ControlFlow<B>,Error> {
+        self.for_each_in_range(..,f)
+    }
+    /// `for_each_in_order` over the keys in `range`, skipping the subtrees outside it.
+    pub fn for_each_in_range<R: RangeBounds<K>,B,F: FnMut(&K,&P,&V) -> ControlFlow<B>>(&self, range: R, mut f: F) -> Result<ControlFlow<B>,Error> {
+        fn visit<K: PartialOrd,P,V,A,R: RangeBounds<K>,B,F: FnMut(&K,&P,&V) -> ControlFlow<B>>(index: &Index<K,P,V,A>, node: NodePtr, range: &R, f: &mut F) -> Result<ControlFlow<B>,IndexError> {
+            if node.is_none() { return Ok(ControlFlow::Continue(())); }
+            let entry = index.get(&node)?;
+            let after = iter::after_start(&entry.key,range.start_bound());
+            let before = iter::before_end(&entry.key,range.end_bound());
+            if after {
+                if let ControlFlow::Break(b) = visit(index,entry.left,range,f)? { return Ok(ControlFlow::Break(b)); }
+            }
+            if after && before {
+                if let ControlFlow::Break(b) = f(&entry.key,&entry.priority,&entry.value) { return Ok(ControlFlow::Break(b)); }
+            }
+            if before { visit(index,entry.right,range,f) } else { Ok(ControlFlow::Continue(())) }
+        }
+
+        visit(&self.index,self.root,&range,&mut f).map_err(Error::Index)
+    }
+    /// Folds the entries with keys in `range` in key order, walking only the subtrees that
+    /// reach into it.
+    pub fn fold_range<R: RangeBounds<K>,T,F: FnMut(T,&K,&P,&V) -> T>(&self, range: R, init: T, mut f: F) -> Result<T,Error> {
+        fn fold<K: PartialOrd,P,V,A,R: RangeBounds<K>,T,F: FnMut(T,&K,&P,&V) -> T>(index: &Index<K,P,V,A>, node: NodePtr, range: &R, acc: T, f: &mut F) -> Result<T,IndexError> {
+            if node.is_none() { return Ok(acc); }
+            let entry = index.get(&node)?;
+            if !iter::after_start(&entry.key,range.start_bound()) { return fold(index,entry.right,range,acc,f); }
+            if !iter::before_end(&entry.key,range.end_bound()) { return fold(index,entry.left,range,acc,f); }
+            let acc = fold(index,entry.left,range,acc,f)?;
+            let acc = f(acc,&entry.key,&entry.priority,&entry.value);
+            fold(index,entry.right,range,acc,f)
+        }
+
+        fold(&self.index,self.root,&range,init,&mut f).map_err(Error::Index)
+    }
+    /// First key for which `pred` is false, like `slice::partition_point`, in O(log n).
+    /// `pred` must hold for a prefix of the keys and fail for the rest; `None` if it holds
+    /// for all of them.
+    pub fn partition_point<F: FnMut(&K) -> bool>(&self, mut pred: F) -> Result<Option<&K>,Error> {
+        let mut found = None;
+        let mut node = self.root;
+        while node.is_some() {
+            let entry = self.index.get(&node).map_err(Error::Index)?;
+            node = if pred(&entry.key) {
+                entry.right
+            } else {
+                found = Some(&entry.key);
+                entry.left
+            };
+        }
+        Ok(found)
+    }
+    /// Greatest entry with a key in `range` by `cmp`, the last one of equals as in
+    /// `Iterator::max_by`. An arbitrary order allows no pruning beyond the range, so this
+    /// visits every entry in it.
+    pub fn max_by_in_range<R: RangeBounds<K>,F: FnMut((&K,&P,&V),(&K,&P,&V)) -> Ordering>(&self, range: R, mut cmp: F) -> Option<(&K,&P,&V)> {
+        self.range(range).max_by(|a,b| cmp(*a,*b))
+    }
+    /// Least entry with a key in `range` by `cmp`, the first one of equals; see `max_by_in_range`.
+    pub fn min_by_in_range<R: RangeBounds<K>,F: FnMut((&K,&P,&V),(&K,&P,&V)) -> Ordering>(&self, range: R, mut cmp: F) -> Option<(&K,&P,&V)> {
+        self.range(range).min_by(|a,b| cmp(*a,*b))
+    }
+    /// Top-ranked entry with a key in `range`, in O(log n): the first one met on the way
+    /// down is above all the others, which lie in its subtree.
+    pub fn max_priority_entry_in_range<B: RangeBounds<K>>(&self, range: B) -> Result<Option<(&K,&P,&V)>,Error> {
+        let mut node = self.root;
+        while node.is_some() {
+            let entry = self.index.get(&node).map_err(Error::Index)?;
+            node = if !iter::after_start(&entry.key,range.start_bound()) {
+                entry.right
+            } else if !iter::before_end(&entry.key,range.end_bound()) {
+                entry.left
+            } else {
+                return Ok(Some((&entry.key,&entry.priority,&entry.value)));
+            };
+        }
+        Ok(None)
+    }
+    /// Entry ranked lowest (the smallest priority under `MaxPolicy`), the other end from
+    /// `peek`. It may sit at any leaf, so this is a scan over the arena.
+    pub fn peek_min(&self) -> Result<Option<(&K,&P,&V)>,Error> {
+        let mut min: Option<&Node<K,P,V,A>> = None;
+        for node in self.index.index.iter().flatten() {
+            if node.left.is_some() || node.right.is_some() { continue; }
+            let lower = match min {
+                Some(min) => H::rank((&node.key,&node.priority),(&min.key,&min.priority)) == Some(Ordering::Less),
+                None => true,
+            };
+            if lower { min = Some(node); }
+        }
+        Ok(min.map(|node| (&node.key,&node.priority,&node.value)))
+    }
+    /// Converts every value with `f`, keeping keys, priorities and the tree shape as they
+    /// are. Aggregates are dropped.
+    pub fn map_values<U,F: FnMut(V) -> U>(self, mut f: F) -> Treap<K,P,U,(),H> {
+        let index = self.index.map(|node| Node {
+            key: node.key, priority: node.priority, value: f(node.value), agg: (), left: node.left, right: node.right,
+            #[cfg(feature = "access-stats")]
+            stats: node.stats,
+        });
+        Treap { root: self.root, index, policy: PhantomData }
+    }
+    /// Converts every key with `f`, which must be strictly increasing, keeping the tree shape
+    /// as it is: O(n) with no comparisons. Debug builds check the new keys and panic if `f`
+    /// broke their order.
+    pub fn map_keys_monotone<J: PartialOrd,F: FnMut(K) -> J>(self, mut f: F) -> Treap<J,P,V,A,H> {
+        let index = self.index.map(|node| Node {
+            key: f(node.key), priority: node.priority, value: node.value, agg: node.agg, left: node.left, right: node.right,
+            #[cfg(feature = "access-stats")]
+            stats: node.stats,
+        });
+        let treap = Treap { root: self.root, index, policy: PhantomData };
+        debug_assert!(treap.iter().zip(treap.iter().skip(1)).all(|((a,_,_),(b,_,_))| a.partial_cmp(b) == Some(Ordering::Less)),"map_keys_monotone: keys out of order");
+        treap
+    }
     pub fn depth(&self) -> Result<usize,Error> {
-        fn depth_node<K,P,V>(index: &Index<K,P,V>, node: NodePtr) -> Result<usize,IndexError> {
+        fn depth_node<K,P,V,A>(index: &Index<K,P,V,A>, node: NodePtr) -> Result<usize,IndexError> {
             if node.is_none() { return Ok(0); }
             let (l,r) = {
                 let entry = index.get(&node)?;
@@ -266,118 +985,783 @@ impl<K: PartialOrd + PartialEq,P: PartialOrd,V> Treap<K,P,V> {
 
         depth_node(&self.index, self.root).map_err(Error::Index)
     }
-    pub fn cut(&mut self, p: &P) -> Result<(),Error> {
-        fn check_node<'t,K,P: PartialOrd,V>(index: &'t mut Index<K,P,V>, node: NodePtr, p: &P) -> Result<bool,IndexError> {
+    /// Removes the entries ranked below `p` by the heap policy; returns how many.
+    pub fn cut(&mut self, p: &P) -> Result<usize,Error> {
+        self.cut_below(Bound::Excluded(p))
+    }
+    /// Removes the entries ranked below `bound` (up to and including it if
+    /// `Included`, everything if `Unbounded`); returns how many.
+    pub fn cut_below(&mut self, bound: Bound<&P>) -> Result<usize,Error> {
+        fn check_node<K: PartialOrd,P: PartialOrd,V,A: Aggregate<V>,H: HeapPolicy>(index: &mut Index<K,P,V,A>, node: NodePtr, bound: Bound<&P>) -> Result<bool,IndexError> {
             if node.is_none() { return Ok(true); }
             let entry = index.get(&node)?;
-            match entry.priority < *p {
+            let below = match bound {
+                Bound::Included(p) => matches!(H::compare(&entry.priority,p),Some(Ordering::Less) | Some(Ordering::Equal)),
+                Bound::Excluded(p) => H::below(&entry.priority,p),
+                Bound::Unbounded => true,
+            };
+            match below {
                 true => {
-                    drop_node(index,node,p)?;
+                    Treap::drop_nodes(index,node)?;
                     Ok(true)
                 },
                 false => {
                     let (l,r) = (entry.left,entry.right);
-                    if check_node(index,l,p)? { index.get_mut(&node)?.left = None; }
-                    if check_node(index,r,p)? { index.get_mut(&node)?.right = None; }
+                    if check_node::<K,P,V,A,H>(index,l,bound)? { index.get_mut(&node)?.left = None; }
+                    if check_node::<K,P,V,A,H>(index,r,bound)? { index.get_mut(&node)?.right = None; }
+                    Treap::update(index,node)?;
                     Ok(false)
                 }
             }
         }
-        fn drop_node<'t,K,P,V>(index: &'t mut Index<K,P,V>, node: NodePtr, p: &P) -> Result<(),IndexError> {
-            if node.is_none() { return Ok(()); }
+
+        let before = self.len();
+        if check_node::<K,P,V,A,H>(&mut self.index,self.root,bound).map_err(Error::Index)? {
+            self.root = None;
+        }
+        Ok(before - self.len())
+    }
+    /// Removes the entries ranked above `bound`, the mirror of `cut_below`; returns how many.
+    pub fn cut_above(&mut self, bound: Bound<&P>) -> Result<usize,Error> {
+        // the entries above form the top of the heap: drop them and merge what hangs below
+        fn strip_node<K: PartialOrd,P: PartialOrd,V,A: Aggregate<V>,H: HeapPolicy>(index: &mut Index<K,P,V,A>, node: NodePtr, bound: Bound<&P>) -> Result<NodePtr,IndexError> {
+            if node.is_none() { return Ok(None); }
+            let priority = &index.get(&node)?.priority;
+            let above = match bound {
+                Bound::Included(p) => matches!(H::compare(priority,p),Some(Ordering::Greater) | Some(Ordering::Equal)),
+                Bound::Excluded(p) => H::above(priority,p),
+                Bound::Unbounded => true,
+            };
+            if !above { return Ok(node); }
             let entry = index.remove(&node)?;
-            drop_node(index,entry.left,p)?;
-            drop_node(index,entry.right,p)
+            let l = strip_node::<K,P,V,A,H>(index,entry.left,bound)?;
+            let r = strip_node::<K,P,V,A,H>(index,entry.right,bound)?;
+            Treap::<K,P,V,A,H>::merge_nodes(index,l,r)
         }
 
-        if check_node(&mut self.index,self.root,p).map_err(Error::Index)? {
-            self.root = None;
+        let before = self.len();
+        self.root = strip_node::<K,P,V,A,H>(&mut self.index,self.root,bound).map_err(Error::Index)?;
+        Ok(before - self.len())
+    }
+    /// Splits into the entries ranked at or above `p` and the rest, like `cut_above` with
+    /// `Bound::Included` but keeping both halves. Costs O(log n) per hot entry.
+    pub fn split_by_priority(mut self, p: &P) -> Result<(Self,Self),Error> {
+        fn strip_node<K: PartialOrd,P: PartialOrd,V,A: Aggregate<V>,H: HeapPolicy>(index: &mut Index<K,P,V,A>, node: NodePtr, p: &P, hot: &mut Vec<Node<K,P,V,A>>) -> Result<NodePtr,IndexError> {
+            if node.is_none() { return Ok(None); }
+            if H::below(&index.get(&node)?.priority,p) { return Ok(node); }
+            let entry = index.remove(&node)?;
+            let (l,r) = (entry.left,entry.right);
+            let l = strip_node::<K,P,V,A,H>(index,l,p,hot)?;
+            hot.push(entry); // in key order
+            let r = strip_node::<K,P,V,A,H>(index,r,p,hot)?;
+            Treap::<K,P,V,A,H>::merge_nodes(index,l,r)
+        }
+
+        let mut hot = Vec::new();
+        self.root = strip_node::<K,P,V,A,H>(&mut self.index,self.root,p,&mut hot).map_err(Error::Index)?;
+        Ok((Treap::build_from_nodes(hot),self))
+    }
+    /// Number of entries ranked above `p`, in time proportional to that number: by heap
+    /// order they form the top of the tree.
+    pub fn count_above(&self, p: &P) -> Result<usize,Error> {
+        let mut count = 0;
+        let mut stack = vec![self.root];
+        while let Some(node) = stack.pop() {
+            if node.is_none() { continue; }
+            let entry = self.index.get(&node).map_err(Error::Index)?;
+            if H::above(&entry.priority,p) {
+                count += 1;
+                stack.push(entry.left);
+                stack.push(entry.right);
+            }
+        }
+        Ok(count)
+    }
+    /// Number of entries `f` accepts. With `prune_below` set, entries ranked below it are
+    /// neither tested nor counted, and by heap order whole subtrees are skipped at once.
+    pub fn count_matching<F: FnMut(&K,&P,&V) -> bool>(&self, mut f: F, prune_below: Option<&P>) -> Result<usize,Error> {
+        let mut count = 0;
+        let mut stack = vec![self.root];
+        while let Some(node) = stack.pop() {
+            if node.is_none() { continue; }
+            let entry = self.index.get(&node).map_err(Error::Index)?;
+            if prune_below.is_some_and(|p| H::below(&entry.priority,p)) { continue; }
+            if f(&entry.key,&entry.priority,&entry.value) { count += 1; }
+            stack.push(entry.left);
+            stack.push(entry.right);
+        }
+        Ok(count)
+    }
+    /// Entries with priority equal to `p`, in key order. Only the entries ranked at or above
+    /// `p` are visited.
+    pub fn find_by_priority(&self, p: &P) -> Result<Vec<(&K,&P,&V)>,Error> {
+        let mut found = Vec::new();
+        let mut stack = vec![self.root];
+        while let Some(node) = stack.pop() {
+            if node.is_none() { continue; }
+            let entry = self.index.get(&node).map_err(Error::Index)?;
+            match H::compare(&entry.priority,p) {
+                Some(Ordering::Equal) => found.push((&entry.key,&entry.priority,&entry.value)),
+                Some(Ordering::Greater) => {},
+                _ => continue,
+            }
+            stack.push(entry.left);
+            stack.push(entry.right);
+        }
+        found.sort_by(|a,b| a.0.partial_cmp(b.0).unwrap_or(Ordering::Equal));
+        Ok(found)
+    }
+    /// Top-ranked entry `f` accepts, found by a best-first search from the root: only the
+    /// entries ranked above it, and their children, are looked at.
+    pub fn find_best<F: FnMut(&K,&P,&V) -> bool>(&self, mut f: F) -> Result<Option<(&K,&P,&V)>,Error> {
+        let mut frontier = BinaryHeap::new();
+        Self::push_ranked(&self.index,&mut frontier,self.root).map_err(Error::Index)?;
+        while let Some(next) = frontier.pop() {
+            let entry = self.index.get(&Some(next.id)).map_err(Error::Index)?;
+            if f(&entry.key,&entry.priority,&entry.value) { return Ok(Some((&entry.key,&entry.priority,&entry.value))); }
+            Self::push_ranked(&self.index,&mut frontier,entry.left).map_err(Error::Index)?;
+            Self::push_ranked(&self.index,&mut frontier,entry.right).map_err(Error::Index)?;
         }
+        Ok(None)
+    }
+    /// Entry counts per priority bucket in one pass. `bounds` must be increasing; bucket
+    /// `i` holds the priorities in `bounds[i-1] .. bounds[i]`, the first and last ones
+    /// everything below and from the ends, so there is one more bucket than bounds.
+    pub fn priority_histogram(&self, bounds: &[P]) -> Vec<usize> {
+        let mut counts = vec![0; bounds.len() + 1];
+        for (_,p,_) in self.iter() {
+            counts[bounds.partition_point(|bound| bound <= p)] += 1;
+        }
+        counts
+    }
+    /// Priority at quantile `q` of all priorities, ascending (`0.5` is the median), found
+    /// by an O(n) selection rather than a sort. `q` is clamped to `0.0 ..= 1.0`.
+    pub fn priority_quantile(&self, q: f64) -> Option<&P> {
+        let mut priorities: Vec<&P> = self.iter().map(|(_,p,_)| p).collect();
+        if priorities.is_empty() { return None; }
+        let rank = ((priorities.len() - 1) as f64 * q.clamp(0.0,1.0) + 0.5) as usize; // rounded without std; NaN gives 0
+        let (_,p,_) = priorities.select_nth_unstable_by(rank,|a,b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+        Some(*p)
+    }
+    /// Replaces every priority `p` by `f(p)` and restores the heap order in one O(n)
+    /// rebuild, for global changes such as decay.
+    pub fn scale_priorities<F: FnMut(P) -> P>(&mut self, mut f: F) -> Result<(),Error> {
+        let mut nodes = Vec::with_capacity(self.len());
+        Treap::take_nodes(&mut self.index,self.root,&mut nodes).map_err(Error::Index)?;
+        *self = Treap::build_from_nodes(nodes.into_iter().map(|mut node| {
+            node.priority = f(node.priority);
+            node
+        }));
+        Ok(())
+    }
+    /// Restores heap order for the current priorities in O(n), keys staying in order. Nodes
+    /// are relinked in place, so handles stay valid.
+    pub fn heapify(&mut self) -> Result<(),Error> {
+        self.relink().map_err(Error::Index)
+    }
+    /// Sets the priorities of many keys in one O(n + m log m) rebuild, for batches touching
+    /// a large part of the treap. Keys not present are skipped; of repeated keys the last
+    /// update counts. Returns how many entries changed priority.
+    pub fn bulk_prioritize<I: IntoIterator<Item = (K,P)>>(&mut self, updates: I) -> Result<usize,Error> {
+        let mut updates: Vec<(K,P)> = updates.into_iter().collect();
+        updates.sort_by(|a,b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal)); // stable
+        let mut updates = updates.into_iter().peekable();
+        let mut nodes = Vec::with_capacity(self.len());
+        Treap::take_nodes(&mut self.index,self.root,&mut nodes).map_err(Error::Index)?;
+        let mut changed = 0;
+        for node in nodes.iter_mut() {
+            let mut update = None;
+            while let Some((k,p)) = updates.next_if(|(k,_)| *k <= node.key) {
+                if k == node.key { update = Some(p); }
+            }
+            if let Some(p) = update {
+                node.priority = p;
+                changed += 1;
+            }
+        }
+        *self = Treap::build_from_nodes(nodes);
+        Ok(changed)
+    }
+    /// `scale_priorities` with the key at hand, for switching to a new scoring function.
+    pub fn map_priorities<F: FnMut(&K,P) -> P>(&mut self, mut f: F) -> Result<(),Error> {
+        let mut nodes = Vec::with_capacity(self.len());
+        Treap::take_nodes(&mut self.index,self.root,&mut nodes).map_err(Error::Index)?;
+        *self = Treap::build_from_nodes(nodes.into_iter().map(|mut node| {
+            node.priority = f(&node.key,node.priority);
+            node
+        }));
+        Ok(())
+    }
+    /// Keeps the entries for which `f` holds, letting it update values on the way, in one
+    /// O(n) pass.
+    pub fn retain_mut<F: FnMut(&K,&P,&mut V) -> bool>(&mut self, mut f: F) -> Result<(),Error> {
+        let mut nodes = Vec::with_capacity(self.len());
+        Treap::take_nodes(&mut self.index,self.root,&mut nodes).map_err(Error::Index)?;
+        nodes.retain_mut(|node| f(&node.key,&node.priority,&mut node.value));
+        *self = Treap::build_from_nodes(nodes);
+        Ok(())
+    }
+    /// Keeps the entries whose key is also in `other`, in one merge walk over both and an
+    /// O(n) rebuild.
+    pub fn retain_keys<Q: PartialOrd,W,B: Aggregate<W>,G: HeapPolicy>(&mut self, other: &Treap<K,Q,W,B,G>) -> Result<(),Error> {
+        let mut nodes = Vec::with_capacity(self.len());
+        Treap::take_nodes(&mut self.index,self.root,&mut nodes).map_err(Error::Index)?;
+        let mut theirs = other.iter().map(|(k,_,_)| k).peekable();
+        nodes.retain(|node| {
+            while theirs.next_if(|k| **k < node.key).is_some() {}
+            theirs.next_if(|k| **k == node.key).is_some()
+        });
+        *self = Treap::build_from_nodes(nodes);
         Ok(())
     }
+    /// Moves the entries for which `f` holds into the first treap and the rest into the
+    /// second, in one O(n) pass.
+    pub fn partition<F: FnMut(&K,&P,&V) -> bool>(mut self, mut f: F) -> Result<(Self,Self),Error> {
+        let mut nodes = Vec::with_capacity(self.len());
+        Treap::take_nodes(&mut self.index,self.root,&mut nodes).map_err(Error::Index)?;
+        let (yes,no): (Vec<_>,Vec<_>) = nodes.into_iter().partition(|node| f(&node.key,&node.priority,&node.value));
+        Ok((Treap::build_from_nodes(yes),Treap::build_from_nodes(no)))
+    }
+    pub fn drain_below(&mut self, p: &P) -> Result<Vec<(K,P,V)>,Error> {
+        fn check_node<K: PartialOrd,P: PartialOrd,V,A: Aggregate<V>,H: HeapPolicy>(index: &mut Index<K,P,V,A>, node: NodePtr, p: &P, out: &mut Vec<Node<K,P,V,A>>) -> Result<bool,IndexError> {
+            if node.is_none() { return Ok(true); }
+            let entry = index.get(&node)?;
+            match H::below(&entry.priority,p) {
+                true => {
+                    Treap::take_nodes(index,node,out)?;
+                    Ok(true)
+                },
+                false => {
+                    let (l,r) = (entry.left,entry.right);
+                    if check_node::<K,P,V,A,H>(index,l,p,out)? { index.get_mut(&node)?.left = None; }
+                    if check_node::<K,P,V,A,H>(index,r,p,out)? { index.get_mut(&node)?.right = None; }
+                    Treap::update(index,node)?;
+                    Ok(false)
+                }
+            }
+        }
+
+        let mut out = Vec::new();
+        if check_node::<K,P,V,A,H>(&mut self.index,self.root,p,&mut out).map_err(Error::Index)? {
+            self.root = None;
+        }
+        Ok(out.into_iter().map(Node::into_entry).collect())
+    }
 }
-impl<K,P: Ord,V> Treap<K,P,V> {
-    pub fn nth_priority(&self, n: usize) -> Result<Option<&P>,Error> {
-        fn nth_priority_node<'t,K,P: Ord,V>(index: &'t Index<K,P,V>, node: NodePtr, n: usize, queue: &mut VecDeque<NodePtr>, pri: &mut Vec<Reverse<&'t P>>) -> Result<(),IndexError> {
-            if node.is_none() { return Ok(()); }
+impl<K: PartialOrd + PartialEq,P: PartialOrd,V,A: Aggregate<V> + Clone,H: HeapPolicy> Treap<K,P,V,A,H> {
+    /// Aggregate of the values with keys in `range`, in O(log n) off the cached subtree aggregates.
+    pub fn aggregate_range<B: RangeBounds<K>>(&self, range: B) -> Result<Option<A>,Error> {
+        fn combine<V,A: Aggregate<V>>(a: Option<A>, b: Option<A>) -> Option<A> {
+            match (a,b) {
+                (Some(a),Some(b)) => Some(A::combine(&a,&b)),
+                (a,None) => a,
+                (None,b) => b,
+            }
+        }
+        // `from`/`to`: the whole subtree is known to be above the start/below the end
+        fn aggregate_node<K: PartialOrd,P,V,A: Aggregate<V> + Clone,B: RangeBounds<K>>(index: &Index<K,P,V,A>, node: NodePtr, range: &B, from: bool, to: bool) -> Result<Option<A>,IndexError> {
+            if node.is_none() { return Ok(None); }
             let entry = index.get(&node)?;
-            let (push,check_ch) = match pri.binary_search(&Reverse(&entry.priority)) {
-                Ok(i) if i < n => (None,true),
-                Err(i) if i < n => (Some(i),true),
-                _ => (None,false),
+            if from && to { return Ok(Some(entry.agg.clone())); }
+            if !from && !iter::after_start(&entry.key,range.start_bound()) {
+                return aggregate_node(index,entry.right,range,from,to);
+            }
+            if !to && !iter::before_end(&entry.key,range.end_bound()) {
+                return aggregate_node(index,entry.left,range,from,to);
+            }
+            let left = aggregate_node(index,entry.left,range,from,true)?;
+            let right = aggregate_node(index,entry.right,range,true,to)?;
+            Ok(combine::<V,A>(combine::<V,A>(left,Some(A::from_value(&entry.value))),right))
+        }
+
+        aggregate_node(&self.index,self.root,&range,false,false).map_err(Error::Index)
+    }
+}
+impl<K: PartialOrd + PartialEq,P: PartialOrd,V: Ord + Clone,H: HeapPolicy> Treap<K,P,V,MinMax<V>,H> {
+    pub fn min_value_in_range<B: RangeBounds<K>>(&self, range: B) -> Result<Option<V>,Error> {
+        Ok(self.aggregate_range(range)?.map(|agg| agg.min))
+    }
+    pub fn max_value_in_range<B: RangeBounds<K>>(&self, range: B) -> Result<Option<V>,Error> {
+        Ok(self.aggregate_range(range)?.map(|agg| agg.max))
+    }
+}
+impl<K: PartialOrd + PartialEq,P: PartialOrd,V: Add<Output = V> + Clone + Default,H: HeapPolicy> Treap<K,P,V,Sum<V>,H> {
+    /// Sum of the values with keys in `range`; `V::default()` when there are none.
+    pub fn sum_range<B: RangeBounds<K>>(&self, range: B) -> Result<V,Error> {
+        Ok(self.aggregate_range(range)?.map(|agg| agg.0).unwrap_or_default())
+    }
+}
+// a `&mut V` handed out here could invalidate cached aggregates, so only plain treaps get one
+impl<K: PartialOrd + PartialEq,P: PartialOrd,V,H: HeapPolicy> Treap<K,P,V,(),H> {
+    pub fn get_mut<'t>(&'t mut self, key: &K) -> Result<Option<(&'t P, &'t mut V)>,Error> {
+        enum Action {
+            Found(NodePtr),
+            Left(NodePtr),
+            Right(NodePtr),
+        }
+        fn search_node<'t,K: PartialOrd + PartialEq,P,V>(index: &'t mut Index<K,P,V>, node: NodePtr, key: &K) -> Result<Option<(&'t P, &'t mut V)>,IndexError> {
+            if node.is_none() { return Ok(None); }
+            let action = {
+                let entry = index.get_mut(&node)?;
+                if entry.key == *key {
+                    Action::Found(node)
+                } else {
+                    if entry.key > *key {
+                        Action::Left(entry.left)
+                    } else {
+                        Action::Right(entry.right)
+                    }
+                }               
             };
-            if let Some(i) = push {
-                pri.insert(i,Reverse(&entry.priority));
+            match action {
+                Action::Found(node) => {
+                    let node_ref = index.get_mut(&node)?;
+                    #[cfg(feature = "access-stats")]
+                    node_ref.stats.touch();
+                    Ok(Some((&node_ref.priority,&mut node_ref.value)))
+                },
+                Action::Left(left) => search_node(index,left,key),
+                Action::Right(right) => search_node(index,right,key),
             }
-            if check_ch {
-                if entry.left.is_some() { queue.push_back(entry.left); }
-                if entry.right.is_some() { queue.push_back(entry.right); }
+        }
+
+        search_node(&mut self.index,self.root,key).map_err(Error::Index)
+    }
+}
+impl<'t,K: PartialOrd + PartialEq,P: PartialOrd,V,A: Aggregate<V>,H: HeapPolicy> IntoIterator for &'t Treap<K,P,V,A,H> {
+    type Item = (&'t K, &'t P, &'t V);
+    type IntoIter = Iter<'t,K,P,V,A>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<K: PartialOrd + PartialEq,P: PartialOrd,V,A: Aggregate<V>,H: HeapPolicy> FromIterator<(K,P,V)> for Treap<K,P,V,A,H> {
+    fn from_iter<I: IntoIterator<Item = (K,P,V)>>(iter: I) -> Treap<K,P,V,A,H> {
+        let mut entries: Vec<(K,P,V)> = iter.into_iter().collect();
+        entries.sort_by(|a,b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+        // last one wins among equal keys, as with repeated inserts
+        let mut unique = Vec::with_capacity(entries.len());
+        let mut entries = entries.into_iter().peekable();
+        while let Some(entry) = entries.next() {
+            match entries.peek() {
+                Some(next) if next.0 == entry.0 => continue,
+                _ => unique.push(entry),
             }
+        }
+        Treap::build_sorted(unique)
+    }
+}
+impl<K: PartialOrd + PartialEq,P: PartialOrd,V,A: Aggregate<V>,H: HeapPolicy> Treap<K,P,V,A,H> {
+    /// `from_iter` that rejects a repeated key instead of keeping its last entry; the
+    /// error carries the smallest such key.
+    pub fn try_from_iter<I: IntoIterator<Item = (K,P,V)>>(iter: I) -> Result<Treap<K,P,V,A,H>,DuplicateKeyError<K>> {
+        let mut entries: Vec<(K,P,V)> = iter.into_iter().collect();
+        entries.sort_by(|a,b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+        if let Some(i) = (1..entries.len()).find(|&i| entries[i - 1].0 == entries[i].0) {
+            return Err(DuplicateKeyError { key: entries.swap_remove(i).0 });
+        }
+        Ok(Treap::build_sorted(entries))
+    }
+}
+
+impl<K: PartialOrd,P: PartialOrd,V,A: Aggregate<V>,H: HeapPolicy> Treap<K,P,V,A,H> {
+    /// `from_iter` that reports a failed allocation as `Error::Alloc` instead of aborting.
+    /// It sorts in place rather than with `from_iter`'s buffer, so each entry carries its
+    /// position meanwhile, to keep the last of equal keys.
+    pub fn try_from_entries<I: IntoIterator<Item = (K,P,V)>>(entries: I) -> Result<Treap<K,P,V,A,H>,Error> {
+        let entries = entries.into_iter();
+        let mut sorted: Vec<(usize,(K,P,V))> = Vec::new();
+        sorted.try_reserve_exact(entries.size_hint().0).map_err(Error::Alloc)?;
+        for entry in entries.enumerate() {
+            sorted.try_reserve(1).map_err(Error::Alloc)?;
+            sorted.push(entry);
+        }
+        sorted.sort_unstable_by(|(i,a),(j,b)| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal).then(i.cmp(j)));
+        let mut builder = SortedBuilder::with_capacity(0);
+        builder.nodes.try_reserve_exact(sorted.len()).map_err(Error::Alloc)?;
+        let mut sorted = sorted.into_iter().peekable();
+        while let Some((_,(key,priority,value))) = sorted.next() {
+            if sorted.peek().is_some_and(|(_,next)| next.0 == key) { continue; }
+            builder.try_push(key,priority,value).map_err(Error::Alloc)?;
+        }
+        builder.try_finish().map_err(Error::Alloc)
+    }
+}
+impl<K: PartialOrd + Clone,P: PartialOrd + Clone,V: Clone,A: Aggregate<V>,H: HeapPolicy> Treap<K,P,V,A,H> {
+    /// Builds in O(n) from entries with strictly increasing keys.
+    pub fn cartesian_from_slice(entries: &[(K,P,V)]) -> Result<Treap<K,P,V,A,H>,Error> {
+        if let Some(i) = (1..entries.len()).find(|&i| entries[i - 1].0.partial_cmp(&entries[i].0) != Some(Ordering::Less)) {
+            return Err(Error::Unsorted(i));
+        }
+        Ok(Treap::build_sorted(entries.iter().cloned()))
+    }
+}
+// the copy gets an arena of its own, so handles into the original don't resolve in it
+impl<K: PartialOrd + Clone,P: PartialOrd + Clone,V: Clone,A: Aggregate<V>,H: HeapPolicy> Clone for Treap<K,P,V,A,H> {
+    fn clone(&self) -> Treap<K,P,V,A,H> {
+        self.clone_range(..)
+    }
+}
+impl<K: PartialOrd + Clone,P: PartialOrd + Clone,V: Clone,A: Aggregate<V>,H: HeapPolicy> Treap<K,P,V,A,H> {
+    /// Copy of the entries with keys in `range` as a treap of its own, in O(k + log n).
+    pub fn clone_range<B: RangeBounds<K>>(&self, range: B) -> Treap<K,P,V,A,H> {
+        Treap::build_sorted(self.range(range).map(|(k,p,v)| (k.clone(),p.clone(),v.clone())))
+    }
+    /// Copies of the `k` top-ranked entries, best first, found by a best-first search
+    /// from the root in O(k log k).
+    pub fn top_k(&self, k: usize) -> Result<Vec<(K,P,V)>,Error> {
+        let top = self.top_nodes(k).map_err(Error::Index)?;
+        top.into_iter().map(|id| {
+            let entry = self.index.get(&Some(id)).map_err(Error::Index)?;
+            Ok((entry.key.clone(),entry.priority.clone(),entry.value.clone()))
+        }).collect()
+    }
+}
+impl<K,P: Ord,V,A,H: HeapPolicy> Treap<K,P,V,A,H> {
+    /// `n`-th highest ranked distinct priority, counting from 1: equal priorities count
+    /// once, so on `[5,5,3]` the second is `3`.
+    pub fn nth_priority(&self, n: usize) -> Result<Option<&P>,Error> {
+        self.nth_ranked_priority(n,true)
+    }
+    /// Priority of the `n`-th highest ranked entry, counting from 1 and counting every
+    /// entry, so on `[5,5,3]` the second is `5`.
+    pub fn nth_entry_priority(&self, n: usize) -> Result<Option<&P>,Error> {
+        self.nth_ranked_priority(n,false)
+    }
+    fn nth_ranked_priority(&self, n: usize, distinct: bool) -> Result<Option<&P>,Error> {
+        fn nth_priority_node<'t,K,P: Ord,V,A,H: HeapPolicy>(index: &'t Index<K,P,V,A>, node: NodePtr, n: usize, distinct: bool, queue: &mut VecDeque<NodePtr>, pri: &mut Vec<&'t P>) -> Result<(),IndexError> {
+            if node.is_none() { return Ok(()); }
+            let entry = index.get(&node)?;
+            // `pri` is kept top-ranked first, at most `n` long
+            let i = pri.partition_point(|p| H::compare(*p,&entry.priority) == Some(Ordering::Greater));
+            let dup = i < pri.len() && *pri[i] == entry.priority;
+            if i >= n { return Ok(()); } // so are its children, which rank no higher
+            if !(distinct && dup) {
+                pri.insert(i,&entry.priority);
+                pri.truncate(n);
+            }
+            if entry.left.is_some() { queue.push_back(entry.left); }
+            if entry.right.is_some() { queue.push_back(entry.right); }
             Ok(())
         }
-        
+
+        if n == 0 { return Ok(None); }
         let mut queue = VecDeque::new();
         let mut pri = Vec::new();
 
-        nth_priority_node(&self.index,self.root,n,&mut queue,&mut pri).map_err(Error::Index)?;       
+        nth_priority_node::<K,P,V,A,H>(&self.index,self.root,n,distinct,&mut queue,&mut pri).map_err(Error::Index)?;
         while let Some(node) = queue.pop_front() {
-            nth_priority_node(&self.index,node,n,&mut queue,&mut pri).map_err(Error::Index)?;           
+            nth_priority_node::<K,P,V,A,H>(&self.index,node,n,distinct,&mut queue,&mut pri).map_err(Error::Index)?;
         }
-        if pri.len() >= n { Ok(Some(pri[n-1].0)) } else { Ok(None) }
+        Ok(pri.get(n - 1).copied())
     }
 }
 
-impl<K: PartialOrd, P: PartialOrd, V> Treap<K,P,V> {    
-    fn split(self, key: &K) -> Result<Split<K,P,V>,IndexError> {
-        fn split_nodes<K: PartialOrd,P,V>(index: &mut Index<K,P,V>, node: NodePtr, key: &K) -> Result<(NodePtr,NodePtr,NodePtr),IndexError> { // left, entry, right
-            if node.is_none() { return Ok((None,None,None)); }
-            let entry = index.get(&node)?;
-            if entry.key == *key {
-                let (l,r) = (entry.left,entry.right);
-                let mut v = index.get_mut(&node)?;
-                v.left = None;
-                v.right = None;
-                Ok((l,node,r))
+// Node helpers that never compare priorities sit on the default policy, so a plain
+// `Treap::update(..)` resolves to them whatever policy the caller has.
+impl<K: PartialOrd,P: PartialOrd,V,A: Aggregate<V>> Treap<K,P,V,A> {
+    fn split_nodes(index: &mut Index<K,P,V,A>, node: NodePtr, key: &K) -> Result<(NodePtr,NodePtr,NodePtr),IndexError> { // left, entry, right
+        if node.is_none() { return Ok((None,None,None)); }
+        let entry = index.get(&node)?;
+        if entry.key == *key {
+            let (l,r) = (entry.left,entry.right);
+            let v = index.get_mut(&node)?;
+            v.left = None;
+            v.right = None;
+            v.agg = A::from_value(&v.value);
+            Ok((l,node,r))
+        } else {
+            if entry.key > *key {
+                // left
+                let nxt = entry.left;
+                let (l,e,r) = Treap::split_nodes(index, nxt, key)?;
+                index.get_mut(&node)?.left = r;
+                Treap::update(index,node)?;
+                Ok((l,e,node))
             } else {
-                if entry.key > *key {
-                    // left
-                    let nxt = entry.left;
-                    let (l,e,r) = split_nodes(index, nxt, key)?;
-                    index.get_mut(&node)?.left = r;
-                    Ok((l,e,node))
-                } else {
-                    // right
-                    let nxt =  entry.right;
-                    let (l,e,r) = split_nodes(index, nxt, key)?;
-                    index.get_mut(&node)?.right = l;
-                    Ok((node,e,r))
-                }
+                // right
+                let nxt =  entry.right;
+                let (l,e,r) = Treap::split_nodes(index, nxt, key)?;
+                index.get_mut(&node)?.right = l;
+                Treap::update(index,node)?;
+                Ok((node,e,r))
             }
         }
-        
+    }
+    fn update(index: &mut Index<K,P,V,A>, node: NodePtr) -> Result<(),IndexError> {
+        let entry = index.get(&node)?;
+        let mut agg = A::from_value(&entry.value);
+        if entry.left.is_some() { agg = A::combine(&index.get(&entry.left)?.agg,&agg); }
+        if entry.right.is_some() { agg = A::combine(&agg,&index.get(&entry.right)?.agg); }
+        index.get_mut(&node)?.agg = agg;
+        Ok(())
+    }
+    fn drop_nodes(index: &mut Index<K,P,V,A>, node: NodePtr) -> Result<(),IndexError> {
+        if node.is_none() { return Ok(()); }
+        let entry = index.remove(&node)?;
+        Treap::drop_nodes(index,entry.left)?;
+        Treap::drop_nodes(index,entry.right)
+    }
+    fn take_nodes(index: &mut Index<K,P,V,A>, node: NodePtr, out: &mut Vec<Node<K,P,V,A>>) -> Result<(),IndexError> { // in key order
+        if node.is_none() { return Ok(()); }
+        let entry = index.remove(&node)?;
+        let (l,r) = (entry.left,entry.right);
+        Treap::take_nodes(index,l,out)?;
+        out.push(entry);
+        Treap::take_nodes(index,r,out)
+    }
+}
+impl<K: PartialOrd,P: PartialOrd,V,A: Aggregate<V>,H: HeapPolicy> Treap<K,P,V,A,H> {
+    fn split(self, key: &K) -> Result<Split<K,P,V,A>,IndexError> {
         let mut index = self.index;
-        let (l,e,r) = split_nodes(&mut index,self.root,key)?;
-        Ok(Split{ left: l, entry: e, right: r, index: index })
+        let (l,e,r) = Treap::split_nodes(&mut index,self.root,key)?;
+        Ok(Split{ left: l, entry: e, right: r, index })
     }
-    fn merge_nodes(index: &mut Index<K,P,V>, left: NodePtr, right: NodePtr) -> Result<NodePtr,IndexError> {
+    fn merge_nodes(index: &mut Index<K,P,V,A>, left: NodePtr, right: NodePtr) -> Result<NodePtr,IndexError> {
         if left.is_none() { return Ok(right); }
         if right.is_none() { return Ok(left); }
-        let (left_p,left_right) = {
+        let (left_e,left_right) = {
             let entry = index.get(&left)?;
-            (&entry.priority,entry.right)
+            ((&entry.key,&entry.priority),entry.right)
         };
-        let (right_p,right_left) = {
+        let (right_e,right_left) = {
             let entry = index.get(&right)?;
-            (&entry.priority,entry.left)
+            ((&entry.key,&entry.priority),entry.left)
         };
-        if left_p > right_p {
-            index.get_mut(&left)?.right = Treap::merge_nodes(index, left_right, right)?;
+        if H::rank(left_e,right_e) == Some(Ordering::Greater) {
+            index.get_mut(&left)?.right = Self::merge_nodes(index, left_right, right)?;
+            Treap::update(index,left)?;
             Ok(left)
         } else {
-            index.get_mut(&right)?.left = Treap::merge_nodes(index, left,right_left)?;
+            index.get_mut(&right)?.left = Self::merge_nodes(index, left,right_left)?;
+            Treap::update(index,right)?;
             Ok(right)
         }
     }
+    fn build_sorted<I: IntoIterator<Item = (K,P,V)>>(entries: I) -> Treap<K,P,V,A,H> { // keys strictly increasing
+        Treap::build_from_nodes(entries.into_iter().map(|(key,priority,value)| Node::new(key,priority,value)))
+    }
+    // `build_sorted` reusing taken nodes, so a rebuild doesn't reset their access stats
+    fn build_from_nodes<I: IntoIterator<Item = Node<K,P,V,A>>>(nodes: I) -> Treap<K,P,V,A,H> { // keys strictly increasing
+        let nodes = nodes.into_iter();
+        let mut builder = SortedBuilder::with_capacity(nodes.size_hint().0);
+        for node in nodes {
+            builder.push_node(node);
+        }
+        builder.finish()
+    }
+    fn relink(&mut self) -> Result<(),IndexError> { // `build_sorted` over the nodes in place
+        let mut order = Vec::with_capacity(self.len()); // ids in key order
+        let mut stack = Vec::new();
+        let mut node = self.root;
+        loop {
+            while let Some(id) = node {
+                stack.push(id);
+                node = self.index.get(&node)?.left;
+            }
+            let id = match stack.pop() {
+                Some(id) => id,
+                None => break,
+            };
+            order.push(id);
+            node = self.index.get(&Some(id))?.right;
+        }
+        let mut spine: Vec<Id> = Vec::new();
+        for id in order {
+            let mut last = None;
+            while let Some(&top) = spine.last() {
+                let (a,b) = (self.index.get(&Some(top))?,self.index.get(&Some(id))?);
+                if H::rank((&a.key,&a.priority),(&b.key,&b.priority)) == Some(Ordering::Greater) { break; }
+                last = spine.pop();
+                Treap::update(&mut self.index,last)?;
+            }
+            if let Some(&top) = spine.last() { self.index.get_mut(&Some(top))?.right = Some(id); }
+            let node = self.index.get_mut(&Some(id))?;
+            node.left = last;
+            node.right = None;
+            spine.push(id);
+        }
+        self.root = spine.first().copied();
+        while let Some(top) = spine.pop() {
+            Treap::update(&mut self.index,Some(top))?;
+        }
+        Ok(())
+    }
+    fn take_below(&mut self, key: &K) -> Result<Vec<(K,P,V)>,IndexError> { // keys strictly below `key`, in order
+        let (l,e,r) = Treap::split_nodes(&mut self.index,self.root,key)?;
+        let mut out = Vec::new();
+        Treap::take_nodes(&mut self.index,l,&mut out)?;
+        self.root = Self::merge_nodes(&mut self.index,e,r)?;
+        Ok(out.into_iter().map(Node::into_entry).collect())
+    }
+    fn merge_index(self, other: Treap<K,P,V,A,H>) -> Result<Join<K,P,V,A>,IndexError> {
+        let remap = |ids: &[NodePtr], root: NodePtr| root.and_then(|id| ids.get(id).copied().flatten());
+        if self.len() >= other.len() {
+            let mut index = self.index;
+            let ids = index.append(other.index)?;
+            Ok(Join{ left: self.root, right: remap(&ids,other.root), index })
+        } else {
+            let mut index = other.index;
+            let ids = index.append(self.index)?;
+            Ok(Join{ left: remap(&ids,self.root), right: other.root, index })
+        }
+    }
+    fn push_ranked<'t>(index: &'t Index<K,P,V,A>, frontier: &mut BinaryHeap<Ranked<'t,K,P,H>>, node: NodePtr) -> Result<(),IndexError> {
+        if let Some(id) = node {
+            let entry = index.get(&node)?;
+            frontier.push(Ranked { key: &entry.key, priority: &entry.priority, id, policy: PhantomData });
+        }
+        Ok(())
+    }
+    fn top_nodes(&self, k: usize) -> Result<Vec<Id>,IndexError> { // the `k` top-ranked, best first
+        let mut frontier = BinaryHeap::new();
+        Self::push_ranked(&self.index,&mut frontier,self.root)?;
+        let mut top = Vec::with_capacity(k.min(self.len()));
+        while top.len() < k {
+            let id = match frontier.pop() {
+                Some(next) => next.id,
+                None => break,
+            };
+            let entry = self.index.get(&Some(id))?;
+            Self::push_ranked(&self.index,&mut frontier,entry.left)?;
+            Self::push_ranked(&self.index,&mut frontier,entry.right)?;
+            top.push(id);
+        }
+        Ok(top)
+    }
+    fn ranks_below(index: &Index<K,P,V,A>, a: NodePtr, b: NodePtr) -> Result<bool,IndexError> {
+        let (a,b) = (index.get(&a)?,index.get(&b)?);
+        Ok(H::rank((&a.key,&a.priority),(&b.key,&b.priority)) == Some(Ordering::Less))
+    }
+    // `flipped`: `a` comes from the second treap, which loses exact ties
+    fn union_nodes(index: &mut Index<K,P,V,A>, a: NodePtr, b: NodePtr, flipped: bool, dropped: &mut Vec<Node<K,P,V,A>>) -> Result<NodePtr,IndexError> {
+        if a.is_none() { return Ok(b); }
+        if b.is_none() { return Ok(a); }
+        let below = {
+            let (a,b) = (index.get(&a)?,index.get(&b)?);
+            match H::rank((&a.key,&a.priority),(&b.key,&b.priority)) {
+                Some(Ordering::Less) => true,
+                Some(Ordering::Equal) => flipped,
+                _ => false,
+            }
+        };
+        let (a,b,flipped) = match below {
+            true => (b,a,!flipped),
+            false => (a,b,flipped),
+        };
+        let mut node = index.take(&a)?;
+        let (l,e,r) = Treap::split_nodes(index,b,&node.key)?;
+        node.left = Self::union_nodes(index,node.left,l,flipped,dropped)?;
+        node.right = Self::union_nodes(index,node.right,r,flipped,dropped)?;
+        index.restore(&a,node)?;
+        Treap::update(index,a)?;
+        if e.is_some() { dropped.push(index.remove(&e)?); }
+        Ok(a)
+    }
+    fn intersection_nodes(index: &mut Index<K,P,V,A>, a: NodePtr, b: NodePtr) -> Result<NodePtr,IndexError> {
+        if a.is_none() || b.is_none() {
+            Treap::drop_nodes(index,a)?;
+            Treap::drop_nodes(index,b)?;
+            return Ok(None);
+        }
+        let (a,b) = match Self::ranks_below(index,a,b)? {
+            true => (b,a),
+            false => (a,b),
+        };
+        let mut node = index.take(&a)?;
+        let (l,e,r) = Treap::split_nodes(index,b,&node.key)?;
+        let left = Self::intersection_nodes(index,node.left,l)?;
+        let right = Self::intersection_nodes(index,node.right,r)?;
+        node.left = left;
+        node.right = right;
+        index.restore(&a,node)?;
+        if e.is_some() {
+            index.remove(&e)?;
+            Treap::update(index,a)?;
+            Ok(a)
+        } else {
+            index.remove(&a)?;
+            Self::merge_nodes(index,left,right)
+        }
+    }
+    fn difference_nodes(index: &mut Index<K,P,V,A>, a: NodePtr, b: NodePtr) -> Result<NodePtr,IndexError> {
+        if a.is_none() {
+            Treap::drop_nodes(index,b)?;
+            return Ok(None);
+        }
+        if b.is_none() { return Ok(a); }
+        let node = index.take(&b)?;
+        let (l,e,r) = Treap::split_nodes(index,a,&node.key)?;
+        let left = Self::difference_nodes(index,l,node.left)?;
+        let right = Self::difference_nodes(index,r,node.right)?;
+        index.restore(&b,node)?;
+        index.remove(&b)?;
+        if e.is_some() { index.remove(&e)?; }
+        Self::merge_nodes(index,left,right)
+    }
+    fn symmetric_difference_nodes(index: &mut Index<K,P,V,A>, a: NodePtr, b: NodePtr) -> Result<NodePtr,IndexError> {
+        if a.is_none() { return Ok(b); }
+        if b.is_none() { return Ok(a); }
+        let (a,b) = match Self::ranks_below(index,a,b)? {
+            true => (b,a),
+            false => (a,b),
+        };
+        let mut node = index.take(&a)?;
+        let (l,e,r) = Treap::split_nodes(index,b,&node.key)?;
+        let left = Self::symmetric_difference_nodes(index,node.left,l)?;
+        let right = Self::symmetric_difference_nodes(index,node.right,r)?;
+        node.left = left;
+        node.right = right;
+        index.restore(&a,node)?;
+        if e.is_some() {
+            index.remove(&e)?;
+            index.remove(&a)?;
+            Self::merge_nodes(index,left,right)
+        } else {
+            Treap::update(index,a)?;
+            Ok(a)
+        }
+    }
+    /// Mergeable-heap union: every entry of both treaps in O(m log(n/m)) for sizes m <= n.
+    /// On a key present in both, the entry ranked higher by the heap policy is kept, the
+    /// one from `self` on a tie.
+    pub fn meld(self, other: Treap<K,P,V,A,H>) -> Result<Treap<K,P,V,A,H>,Error> {
+        self.union(other).map_err(Error::Index)
+    }
+    /// Moves the entries of `other` in, as `meld` does: of two entries with the same key the
+    /// higher ranked stays, the one already here on a tie. Returns the others in key order.
+    pub fn absorb(&mut self, other: Treap<K,P,V,A,H>) -> Result<Vec<(K,P,V)>,Error> {
+        let mut jn = core::mem::take(self).merge_index(other).map_err(Error::Index)?;
+        let mut dropped = Vec::new();
+        self.root = Self::union_nodes(&mut jn.index,jn.left,jn.right,false,&mut dropped).map_err(Error::Index)?;
+        self.index = jn.index;
+        let mut losers: Vec<(K,P,V)> = dropped.into_iter().map(|node| (node.key,node.priority,node.value)).collect();
+        losers.sort_by(|a,b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+        Ok(losers)
+    }
+    /// Entries whose key is in exactly one of the treaps, in O(m log(n/m)) for sizes m <= n.
+    pub fn symmetric_difference(self, other: Treap<K,P,V,A,H>) -> Result<Treap<K,P,V,A,H>,Error> {
+        let mut jn = self.merge_index(other).map_err(Error::Index)?;
+        let root = Self::symmetric_difference_nodes(&mut jn.index,jn.left,jn.right).map_err(Error::Index)?;
+        Ok(Treap { root, index: jn.index, policy: PhantomData })
+    }
+    fn union(self, other: Treap<K,P,V,A,H>) -> Result<Treap<K,P,V,A,H>,IndexError> {
+        let mut jn = self.merge_index(other)?;
+        let root = Self::union_nodes(&mut jn.index,jn.left,jn.right,false,&mut Vec::new())?;
+        Ok(Treap { root, index: jn.index, policy: PhantomData })
+    }
+    fn intersection(self, other: Treap<K,P,V,A,H>) -> Result<Treap<K,P,V,A,H>,IndexError> {
+        let mut jn = self.merge_index(other)?;
+        let root = Self::intersection_nodes(&mut jn.index,jn.left,jn.right)?;
+        Ok(Treap { root, index: jn.index, policy: PhantomData })
+    }
+    fn difference(self, other: Treap<K,P,V,A,H>) -> Result<Treap<K,P,V,A,H>,IndexError> {
+        let mut jn = self.merge_index(other)?;
+        let root = Self::difference_nodes(&mut jn.index,jn.left,jn.right)?;
+        Ok(Treap { root, index: jn.index, policy: PhantomData })
+    }
 }
 
 /*
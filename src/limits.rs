@@ -0,0 +1,110 @@
+use core::fmt;
+use core::cmp::Ordering;
+use alloc::vec::Vec;
+
+use crate::{Treap,IndexError,NodePtr,Node,Error,Aggregate,HeapPolicy};
+
+/// Caps for `Treap::checked_insert`; `None` leaves that one unchecked.
+#[derive(Debug,Default,Clone,Copy,PartialEq,Eq)]
+pub struct Limits {
+    pub entries: Option<usize>,
+    pub capacity: Option<usize>, // arena slots, free ones included
+    pub depth: Option<usize>,
+}
+
+/// The limit a `checked_insert` would have passed, with its value.
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum Limit {
+    Entries(usize),
+    Capacity(usize),
+    Depth(usize),
+}
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Limit::Entries(n) => write!(f,"limit of {} entries",n),
+            Limit::Capacity(n) => write!(f,"limit of {} arena slots",n),
+            Limit::Depth(n) => write!(f,"depth limit of {}",n),
+        }
+    }
+}
+
+impl<K: PartialOrd,P: PartialOrd,V,A: Aggregate<V>,H: HeapPolicy> Treap<K,P,V,A,H> {
+    /// `insert` that fails with `Error::LimitExceeded`, leaving the treap untouched, if it
+    /// would take the treap past one of `limits`. Replacing an entry never counts against
+    /// `entries` or `capacity`, and under a `capacity` the arena's allocation stops at the
+    /// cap instead of doubling past it. `depth` is checked exactly but only where the
+    /// insert reshapes the tree, in time proportional to that part, so it holds for the
+    /// whole tree as long as every insert is checked against it.
+    pub fn checked_insert(&mut self, key: K, priority: P, value: V, limits: Limits) -> Result<Option<(P,V)>,Error> {
+        let replaces = self.get(&key)?.is_some();
+        if let Some(max) = limits.entries {
+            if !replaces && self.len() >= max { return Err(Error::LimitExceeded(Limit::Entries(max))); }
+        }
+        let grows = !replaces && self.index.reuse.is_empty();
+        if let Some(max) = limits.capacity {
+            if grows && self.index.index.len() >= max { return Err(Error::LimitExceeded(Limit::Capacity(max))); }
+        }
+        if let Some(max) = limits.depth {
+            if self.depth_after(&key,&priority).map_err(Error::Index)? > max { return Err(Error::LimitExceeded(Limit::Depth(max))); }
+        }
+        if let (true,Some(max)) = (grows,limits.capacity) {
+            self.index.reserve_within(max);
+        }
+        self.insert(key,priority,value)
+    }
+
+    // Depth of the deepest entry an insert of `key` would move. The tree changes only
+    // under the first node on the search path that is the entry replaced or ranks below
+    // the new one; that subtree becomes the treap of its entries with the new one in, whose
+    // height comes off the stack of its right spine as the entries go by in key order.
+    fn depth_after(&self, key: &K, priority: &P) -> Result<usize,IndexError> {
+        let mut depth = 1;
+        let mut top = self.root;
+        while top.is_some() {
+            let entry = self.index.get(&top)?;
+            if entry.key.partial_cmp(key) == Some(Ordering::Equal)
+                || H::rank((key,priority),(&entry.key,&entry.priority)) == Some(Ordering::Greater) { break; }
+            top = if entry.key > *key { entry.left } else { entry.right };
+            depth += 1;
+        }
+
+        let mut spine: Vec<((&K,&P),usize)> = Vec::new(); // with the height of its left subtree
+        let mut push = |item| {
+            let mut below = 0; // height of the spine popped so far
+            while let Some(&(last,left)) = spine.last() {
+                if H::rank(last,item) == Some(Ordering::Greater) { break; }
+                spine.pop();
+                below = 1 + left.max(below);
+            }
+            spine.push((item,below));
+        };
+        let mut pending = Some((key,priority));
+        let mut stack: Vec<&Node<K,P,V,A>> = Vec::new();
+        let mut node: NodePtr = top;
+        loop {
+            while node.is_some() {
+                let entry = self.index.get(&node)?;
+                stack.push(entry);
+                node = entry.left;
+            }
+            let entry = match stack.pop() {
+                Some(entry) => entry,
+                None => break,
+            };
+            match entry.key.partial_cmp(key) {
+                Some(Ordering::Less) => push((&entry.key,&entry.priority)),
+                Some(Ordering::Equal) => if let Some(new) = pending.take() { push(new) }, // in the old one's place
+                _ => {
+                    if let Some(new) = pending.take() { push(new) }
+                    push((&entry.key,&entry.priority));
+                },
+            }
+            node = entry.right;
+        }
+        if let Some(new) = pending { push(new) }
+        let height = spine.iter().rev().fold(0,|below,&(_,left)| 1 + left.max(below));
+        Ok(depth - 1 + height)
+    }
+}
+
@@ -0,0 +1,17 @@
+/// Builds a `Treap` from `key => (priority, value)` pairs in one bulk pass.
+#[macro_export]
+macro_rules! treap {
+    () => { $crate::Treap::new() };
+    ( $( $key:expr => ($priority:expr, $value:expr) ),+ $(,)? ) => {
+        <$crate::Treap<_,_,_> as ::core::iter::FromIterator<_>>::from_iter([ $( ($key,$priority,$value) ),+ ])
+    };
+}
+
+/// Builds a `TreapSet` from a list of keys in one bulk pass.
+#[macro_export]
+macro_rules! treap_set {
+    () => { $crate::TreapSet::new() };
+    ( $( $key:expr ),+ $(,)? ) => {
+        <$crate::TreapSet<_> as ::core::iter::FromIterator<_>>::from_iter([ $( $key ),+ ])
+    };
+}
@@ -0,0 +1,133 @@
+use core::ops::RangeBounds;
+use core::hash::Hash;
+#[cfg(feature = "std")]
+use core::iter::FromIterator;
+
+use rand::{RngCore,SeedableRng};
+use rand::rngs::StdRng;
+
+use crate::{Treap,Error,PrioritySource,KeyHash};
+#[cfg(feature = "std")]
+use crate::priority::entropy_rng;
+
+/// Ordered map on top of `Treap` with priorities taken from a `PrioritySource` (an RNG by default).
+#[derive(Debug)]
+pub struct TreapMap<K,V,R = StdRng> {
+    treap: Treap<K,u64,V>,
+    source: R,
+}
+#[cfg(feature = "std")]
+impl<K: PartialOrd,V> Default for TreapMap<K,V> {
+    fn default() -> TreapMap<K,V> {
+        TreapMap::new()
+    }
+}
+#[cfg(feature = "std")]
+impl<K: PartialOrd,V> TreapMap<K,V> {
+    pub fn new() -> TreapMap<K,V> {
+        TreapMap::with_rng(entropy_rng())
+    }
+}
+impl<K: PartialOrd,V,R: SeedableRng + RngCore> TreapMap<K,V,R> {
+    pub fn with_seed(seed: u64) -> TreapMap<K,V,R> {
+        TreapMap::with_rng(R::seed_from_u64(seed))
+    }
+}
+impl<K: PartialOrd,V,R: RngCore> TreapMap<K,V,R> {
+    pub fn with_rng(rng: R) -> TreapMap<K,V,R> {
+        TreapMap::with_source(rng)
+    }
+}
+impl<K: PartialOrd + Hash,V> TreapMap<K,V,KeyHash> {
+    pub fn with_key_hash(seed: u64) -> TreapMap<K,V,KeyHash> {
+        TreapMap::with_source(KeyHash::new(seed))
+    }
+}
+impl<K: PartialOrd,V,R: PrioritySource<K>> TreapMap<K,V,R> {
+    pub fn with_source(source: R) -> TreapMap<K,V,R> {
+        TreapMap { treap: Treap::new(), source }
+    }
+    pub fn len(&self) -> usize {
+        self.treap.len()
+    }
+    /// Draws fresh priorities for every key and rebuilds in O(n), for when a run of unlucky
+    /// ones left the tree deep. With `KeyHash` the priorities, and so the shape, stay the same.
+    pub fn rebalance(&mut self) -> Result<(),Error> {
+        let source = &mut self.source;
+        self.treap.map_priorities(|key,_| source.priority(key))
+    }
+    pub fn is_empty(&self) -> bool {
+        self.treap.is_empty()
+    }
+    pub fn insert(&mut self, key: K, value: V) -> Result<Option<V>,Error> {
+        let priority = self.source.priority(&key);
+        Ok(self.treap.insert(key,priority,value)?.map(|(_,v)| v))
+    }
+    pub fn remove(&mut self, key: &K) -> Result<Option<V>,Error> {
+        Ok(self.treap.remove(key)?.map(|(_,v)| v))
+    }
+    pub fn get(&self, key: &K) -> Result<Option<&V>,Error> {
+        Ok(self.treap.get(key)?.map(|(_,v)| v))
+    }
+    pub fn get_mut(&mut self, key: &K) -> Result<Option<&mut V>,Error> {
+        Ok(self.treap.get_mut(key)?.map(|(_,v)| v))
+    }
+    pub fn contains_key(&self, key: &K) -> Result<bool,Error> {
+        Ok(self.treap.priority(key)?.is_some())
+    }
+    pub fn iter(&self) -> Iter<'_,K,V> {
+        Iter { iter: self.treap.iter() }
+    }
+    pub fn range<B: RangeBounds<K>>(&self, range: B) -> Range<'_,K,V> {
+        Range { iter: self.treap.range(range) }
+    }
+}
+
+impl<'t,K: PartialOrd,V,R: PrioritySource<K>> IntoIterator for &'t TreapMap<K,V,R> {
+    type Item = (&'t K, &'t V);
+    type IntoIter = Iter<'t,K,V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K: PartialOrd,V> FromIterator<(K,V)> for TreapMap<K,V> {
+    fn from_iter<I: IntoIterator<Item = (K,V)>>(iter: I) -> TreapMap<K,V> {
+        let mut source = entropy_rng();
+        let treap = iter.into_iter().map(|(key,value)| {
+            let priority = source.priority(&key);
+            (key,priority,value)
+        }).collect();
+        TreapMap { treap, source }
+    }
+}
+
+pub struct Iter<'t,K,V> {
+    pub(crate) iter: crate::Iter<'t,K,u64,V>,
+}
+impl<'t,K,V> Iterator for Iter<'t,K,V> {
+    type Item = (&'t K, &'t V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|(k,_,v)| (k,v))
+    }
+    fn size_hint(&self) -> (usize,Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+pub struct Range<'t,K,V> {
+    pub(crate) iter: crate::Range<'t,K,u64,V>,
+}
+impl<'t,K,V> Iterator for Range<'t,K,V> {
+    type Item = (&'t K, &'t V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|(k,_,v)| (k,v))
+    }
+    fn size_hint(&self) -> (usize,Option<usize>) {
+        self.iter.size_hint()
+    }
+}
@@ -0,0 +1,72 @@
+use core::iter::Peekable;
+use core::marker::PhantomData;
+use core::cmp::Ordering;
+use alloc::vec::Vec;
+
+use crate::{Treap,Iter,Aggregate,HeapPolicy,MaxPolicy};
+
+/// Which entries `Treap::merged_iter` yields when a key is in more than one treap.
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum Ties {
+    /// Every one, in the order of the treaps.
+    All,
+    /// The one from the earliest treap.
+    First,
+    /// The one from the latest treap.
+    Last,
+    /// The top-ranked one, the earliest of equals.
+    Top,
+}
+
+/// Entries of several treaps in key order, merged lazily.
+pub struct Merged<'t,K,P,V,A = (),H = MaxPolicy> {
+    iters: Vec<Peekable<Iter<'t,K,P,V,A>>>,
+    ties: Ties,
+    policy: PhantomData<H>,
+}
+
+impl<K: PartialOrd,P: PartialOrd,V,A: Aggregate<V>,H: HeapPolicy> Treap<K,P,V,A,H> {
+    /// Lazy k-way merge of `treaps`, e.g. the shards of one data set, into a single stream
+    /// in key order; each step compares the next key of every treap.
+    pub fn merged_iter<'t>(treaps: &[&'t Treap<K,P,V,A,H>], ties: Ties) -> Merged<'t,K,P,V,A,H> {
+        Merged { iters: treaps.iter().map(|treap| treap.iter().peekable()).collect(), ties, policy: PhantomData }
+    }
+}
+
+impl<'t,K: PartialOrd,P: PartialOrd,V,A,H: HeapPolicy> Iterator for Merged<'t,K,P,V,A,H> {
+    type Item = (&'t K, &'t P, &'t V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut first: Option<(&K,&P)> = None;
+        let mut picked = 0;
+        for (i,iter) in self.iters.iter_mut().enumerate() {
+            let (key,priority,_) = match iter.peek() {
+                Some(&entry) => entry,
+                None => continue,
+            };
+            let better = match first {
+                None => true,
+                Some((min,top)) => match key.partial_cmp(min) {
+                    Some(Ordering::Less) => true,
+                    Some(Ordering::Equal) => match self.ties {
+                        Ties::All | Ties::First => false,
+                        Ties::Last => true,
+                        Ties::Top => H::compare(priority,top) == Some(Ordering::Greater),
+                    },
+                    _ => false,
+                },
+            };
+            if better {
+                first = Some((key,priority));
+                picked = i;
+            }
+        }
+        let (key,_) = first?;
+        if self.ties != Ties::All {
+            for (i,iter) in self.iters.iter_mut().enumerate() {
+                if i != picked { iter.next_if(|entry| entry.0 == key); }
+            }
+        }
+        self.iters[picked].next()
+    }
+}
@@ -0,0 +1,151 @@
+use core::ops::RangeBounds;
+use core::hash::Hash;
+use alloc::vec::Vec;
+use alloc::collections::VecDeque;
+use alloc::collections::vec_deque;
+
+use rand::{RngCore,SeedableRng};
+use rand::rngs::StdRng;
+
+use crate::{TreapMap,Error,PrioritySource,KeyHash};
+#[cfg(feature = "std")]
+use crate::priority::entropy_rng;
+use crate::map;
+
+/// Ordered map allowing duplicate keys; values of one key are kept in insertion order, in
+/// a deque so `remove_one` takes the oldest in O(1).
+#[derive(Debug)]
+pub struct TreapMultimap<K,V,R = StdRng> {
+    map: TreapMap<K,VecDeque<V>,R>,
+    len: usize,
+}
+#[cfg(feature = "std")]
+impl<K: PartialOrd,V> Default for TreapMultimap<K,V> {
+    fn default() -> TreapMultimap<K,V> {
+        TreapMultimap::new()
+    }
+}
+#[cfg(feature = "std")]
+impl<K: PartialOrd,V> TreapMultimap<K,V> {
+    pub fn new() -> TreapMultimap<K,V> {
+        TreapMultimap::with_rng(entropy_rng())
+    }
+}
+impl<K: PartialOrd,V,R: SeedableRng + RngCore> TreapMultimap<K,V,R> {
+    pub fn with_seed(seed: u64) -> TreapMultimap<K,V,R> {
+        TreapMultimap::with_rng(R::seed_from_u64(seed))
+    }
+}
+impl<K: PartialOrd,V,R: RngCore> TreapMultimap<K,V,R> {
+    pub fn with_rng(rng: R) -> TreapMultimap<K,V,R> {
+        TreapMultimap::with_source(rng)
+    }
+}
+impl<K: PartialOrd + Hash,V> TreapMultimap<K,V,KeyHash> {
+    pub fn with_key_hash(seed: u64) -> TreapMultimap<K,V,KeyHash> {
+        TreapMultimap::with_source(KeyHash::new(seed))
+    }
+}
+impl<K: PartialOrd,V,R: PrioritySource<K>> TreapMultimap<K,V,R> {
+    pub fn with_source(source: R) -> TreapMultimap<K,V,R> {
+        TreapMultimap { map: TreapMap::with_source(source), len: 0 }
+    }
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    pub fn keys_len(&self) -> usize {
+        self.map.len()
+    }
+    pub fn insert(&mut self, key: K, value: V) -> Result<(),Error> {
+        match self.map.get_mut(&key)? {
+            Some(values) => values.push_back(value),
+            None => { self.map.insert(key,VecDeque::from([value]))?; },
+        }
+        self.len += 1;
+        Ok(())
+    }
+    /// Values of `key`, oldest first.
+    pub fn get_all(&self, key: &K) -> Result<vec_deque::Iter<'_,V>,Error> {
+        Ok(self.map.get(key)?.map(|values| values.iter()).unwrap_or_default())
+    }
+    pub fn contains_key(&self, key: &K) -> Result<bool,Error> {
+        self.map.contains_key(key)
+    }
+    pub fn remove_one(&mut self, key: &K) -> Result<Option<V>,Error> {
+        let (value,emptied) = match self.map.get_mut(key)? {
+            Some(values) => {
+                let value = values.pop_front();
+                (value,values.is_empty())
+            },
+            None => (None,false),
+        };
+        if emptied { self.map.remove(key)?; }
+        if value.is_some() { self.len -= 1; }
+        Ok(value)
+    }
+    pub fn remove_all(&mut self, key: &K) -> Result<Vec<V>,Error> {
+        let values = self.map.remove(key)?.unwrap_or_default();
+        self.len -= values.len();
+        Ok(values.into())
+    }
+    pub fn iter(&self) -> Iter<'_,K,V> {
+        Iter { iter: Flatten { iter: self.map.iter(), current: None } }
+    }
+    pub fn range<B: RangeBounds<K>>(&self, range: B) -> Range<'_,K,V> {
+        Range { iter: Flatten { iter: self.map.range(range), current: None } }
+    }
+}
+
+impl<'t,K: PartialOrd,V,R: PrioritySource<K>> IntoIterator for &'t TreapMultimap<K,V,R> {
+    type Item = (&'t K, &'t V);
+    type IntoIter = Iter<'t,K,V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+struct Flatten<'t,K,V,I> {
+    iter: I,
+    current: Option<(&'t K, vec_deque::Iter<'t,V>)>,
+}
+impl<'t,K,V,I: Iterator<Item = (&'t K, &'t VecDeque<V>)>> Iterator for Flatten<'t,K,V,I> {
+    type Item = (&'t K, &'t V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((key,values)) = &mut self.current {
+                if let Some(value) = values.next() {
+                    return Some((*key,value));
+                }
+            }
+            let (key,values) = self.iter.next()?;
+            self.current = Some((key,values.iter()));
+        }
+    }
+}
+
+pub struct Iter<'t,K,V> {
+    iter: Flatten<'t,K,V,map::Iter<'t,K,VecDeque<V>>>,
+}
+impl<'t,K,V> Iterator for Iter<'t,K,V> {
+    type Item = (&'t K, &'t V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next()
+    }
+}
+
+pub struct Range<'t,K,V> {
+    iter: Flatten<'t,K,V,map::Range<'t,K,VecDeque<V>>>,
+}
+impl<'t,K,V> Iterator for Range<'t,K,V> {
+    type Item = (&'t K, &'t V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next()
+    }
+}
@@ -0,0 +1,105 @@
+use alloc::vec::Vec;
+
+use crate::{Treap,Iter,Error};
+
+/// Change made to an `ObservedTreap`.
+#[derive(Debug)]
+pub enum Event<'a,K,P,V> {
+    Inserted { key: &'a K, priority: &'a P, value: &'a V },
+    /// Sent once the new entry is in, with the one it took the place of.
+    Replaced { key: &'a K, old: (&'a P,&'a V), new: (&'a P,&'a V) },
+    Removed { key: &'a K, priority: &'a P, value: &'a V },
+    /// Removed for its priority, by `pop` or `drain_below`.
+    Evicted { key: &'a K, priority: &'a P, value: &'a V },
+}
+
+/// Receiver of the changes to an `ObservedTreap`; any `FnMut(Event)` closure is one.
+pub trait Observer<K,P,V> {
+    fn notify(&mut self, event: Event<'_,K,P,V>);
+}
+impl<K,P,V,F: FnMut(Event<'_,K,P,V>)> Observer<K,P,V> for F {
+    fn notify(&mut self, event: Event<'_,K,P,V>) {
+        self(event)
+    }
+}
+
+/// `Treap` reporting every insert, replace, remove and eviction to an observer, e.g. to
+/// keep an external index in step.
+#[derive(Debug)]
+pub struct ObservedTreap<K,P,V,O> {
+    treap: Treap<K,P,V>,
+    observer: O,
+}
+impl<K: PartialOrd,P: PartialOrd,V,O: Observer<K,P,V>> ObservedTreap<K,P,V,O> {
+    pub fn new(observer: O) -> ObservedTreap<K,P,V,O> {
+        ObservedTreap { treap: Treap::new(), observer }
+    }
+    pub fn len(&self) -> usize {
+        self.treap.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.treap.is_empty()
+    }
+    /// The underlying treap, for read-only queries.
+    pub fn treap(&self) -> &Treap<K,P,V> {
+        &self.treap
+    }
+    pub fn observer(&self) -> &O {
+        &self.observer
+    }
+    pub fn observer_mut(&mut self) -> &mut O {
+        &mut self.observer
+    }
+    pub fn into_parts(self) -> (Treap<K,P,V>,O) {
+        (self.treap,self.observer)
+    }
+    pub fn insert(&mut self, key: K, priority: P, value: V) -> Result<Option<(P,V)>,Error> {
+        let (new,old) = self.treap.insert_entry(key,priority,value)?;
+        let entry = self.treap.index.get(&new).map_err(Error::Index)?;
+        let (key,priority,value) = (&entry.key,&entry.priority,&entry.value);
+        match &old {
+            Some((p,v)) => self.observer.notify(Event::Replaced { key, old: (p,v), new: (priority,value) }),
+            None => self.observer.notify(Event::Inserted { key, priority, value }),
+        }
+        Ok(old)
+    }
+    pub fn remove(&mut self, key: &K) -> Result<Option<(P,V)>,Error> {
+        let removed = self.treap.remove(key)?;
+        if let Some((priority,value)) = &removed {
+            self.observer.notify(Event::Removed { key, priority, value });
+        }
+        Ok(removed)
+    }
+    pub fn pop(&mut self) -> Result<Option<(K,P,V)>,Error> {
+        let popped = self.treap.pop()?;
+        if let Some((key,priority,value)) = &popped {
+            self.observer.notify(Event::Evicted { key, priority, value });
+        }
+        Ok(popped)
+    }
+    pub fn drain_below(&mut self, p: &P) -> Result<Vec<(K,P,V)>,Error> {
+        let drained = self.treap.drain_below(p)?;
+        for (key,priority,value) in &drained {
+            self.observer.notify(Event::Evicted { key, priority, value });
+        }
+        Ok(drained)
+    }
+    pub fn peek(&self) -> Result<Option<(&K,&P,&V)>,Error> {
+        self.treap.peek()
+    }
+    pub fn get(&self, key: &K) -> Result<Option<(&P,&V)>,Error> {
+        self.treap.get(key)
+    }
+    pub fn iter(&self) -> Iter<'_,K,P,V> {
+        self.treap.iter()
+    }
+}
+
+impl<'t,K: PartialOrd,P: PartialOrd,V,O: Observer<K,P,V>> IntoIterator for &'t ObservedTreap<K,P,V,O> {
+    type Item = (&'t K, &'t P, &'t V);
+    type IntoIter = Iter<'t,K,P,V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
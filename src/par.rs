@@ -0,0 +1,255 @@
+use core::marker::PhantomData;
+use core::cmp::Ordering;
+use core::ops::{RangeBounds,Deref,DerefMut};
+use core::sync::atomic::{AtomicBool,Ordering as AtomicOrdering};
+use alloc::vec::Vec;
+
+use rayon::iter::{ParallelIterator,IntoParallelIterator,IntoParallelRefIterator,IntoParallelRefMutIterator,ParallelExtend};
+use rayon::slice::ParallelSliceMut;
+use rayon::iter::plumbing::UnindexedConsumer;
+
+use crate::{Treap,Node,Index,Id,NodePtr,Aggregate,HeapPolicy,Error,IndexError};
+use crate::iter::{after_start,before_end};
+
+const LEVELS: u32 = 4; // extra fork levels beyond one part per thread, for load balance
+
+// Iterators split work over the node arena rather than along the tree: every live node
+// sits in the arena, so its halves are disjoint, evenly sized and need no pointer chasing.
+// Entries therefore come in no particular order.
+
+impl<K: PartialOrd + Sync,P: PartialOrd + Sync,V: Sync,A: Aggregate<V> + Sync,H: HeapPolicy> Treap<K,P,V,A,H> {
+    pub fn par_iter(&self) -> impl ParallelIterator<Item = (&K,&P,&V)> + '_ {
+        self.index.index.par_iter().filter_map(|node| node.as_ref().map(|node| (&node.key,&node.priority,&node.value)))
+    }
+}
+impl<K: PartialOrd + Send + Sync,P: PartialOrd + Send + Sync,V: Send,H: HeapPolicy> Treap<K,P,V,(),H> {
+    // plain treaps only, like `get_mut`
+    pub fn par_iter_mut(&mut self) -> impl ParallelIterator<Item = (&K,&P,&mut V)> + '_ {
+        self.index.index.par_iter_mut().filter_map(|node| node.as_mut().map(|node| (&node.key,&node.priority,&mut node.value)))
+    }
+}
+impl<K: PartialOrd + Send,P: PartialOrd + Send,V: Send,A: Aggregate<V> + Send,H: HeapPolicy + Send> Treap<K,P,V,A,H> {
+    /// `meld` that recurses into both sides of every split in parallel, down to a few
+    /// levels more than needed to give each thread a part.
+    pub fn par_meld(self, other: Treap<K,P,V,A,H>) -> Result<Treap<K,P,V,A,H>,Error> {
+        let levels = match rayon::current_num_threads() {
+            1 => 0,
+            n => n.next_power_of_two().trailing_zeros() + LEVELS,
+        };
+        let mut jn = self.merge_index(other).map_err(Error::Index)?;
+        let mut dropped = Vec::new();
+        let root = par_union_nodes::<K,P,V,A,H>(&Slots::new(&mut jn.index.index),jn.left,jn.right,false,levels,&mut dropped).map_err(Error::Index)?;
+        for id in dropped {
+            jn.index.remove(&Some(id)).map_err(Error::Index)?;
+        }
+        Ok(Treap { root, index: jn.index, policy: PhantomData })
+    }
+}
+
+impl<K: PartialOrd + Send,P: PartialOrd + Send,V: Send,A: Aggregate<V> + Send,H: HeapPolicy + Send> Treap<K,P,V,A,H> {
+    /// Adds the entries of a parallel iterator: they are sorted, built into one treap per
+    /// key range and melded in, all in parallel. As in `meld`, the higher priority wins on
+    /// a repeated key.
+    pub fn try_par_extend<I: IntoParallelIterator<Item = (K,P,V)>>(&mut self, entries: I) -> Result<(),Error> {
+        let mut entries: Vec<(K,P,V)> = entries.into_par_iter().collect();
+        entries.par_sort_by(|a,b| match a.0.partial_cmp(&b.0) {
+            Some(Ordering::Equal) | None => H::compare(&b.1,&a.1).unwrap_or(Ordering::Equal), // highest ranked first
+            Some(order) => order,
+        });
+        entries.dedup_by(|next,kept| next.0 == kept.0);
+        let size = usize::max(entries.len() / rayon::current_num_threads(),1);
+        let mut parts = Vec::new();
+        while entries.len() > size {
+            parts.push(entries.split_off(entries.len() - size));
+        }
+        parts.push(entries);
+        let incoming = parts.into_par_iter()
+            .map(|part| Ok(Treap::build_sorted(part)))
+            .reduce(|| Ok(Treap::new()),|a: Result<Treap<K,P,V,A,H>,Error>,b| a?.par_meld(b?))?;
+        *self = core::mem::take(self).par_meld(incoming)?;
+        Ok(())
+    }
+}
+impl<K: PartialOrd + Send,P: PartialOrd + Send,V: Send,A: Aggregate<V> + Send,H: HeapPolicy + Send> ParallelExtend<(K,P,V)> for Treap<K,P,V,A,H> {
+    fn par_extend<I: IntoParallelIterator<Item = (K,P,V)>>(&mut self, entries: I) {
+        let _ = self.try_par_extend(entries); // fails only on a corrupted arena
+    }
+}
+
+impl<K: PartialOrd + Sync,P: PartialOrd + Sync,V: Sync,A: Aggregate<V> + Sync,H: HeapPolicy> Treap<K,P,V,A,H> {
+    /// Folds the entries with keys in `range`, subtrees in parallel: each task folds its
+    /// entries in key order starting from `init`, and `reduce` joins neighbouring results,
+    /// left one first. `init` must be an identity of `reduce`.
+    pub fn par_fold_range<B,T,F,R>(&self, range: B, init: T, fold: F, reduce: R) -> Result<T,Error>
+    where B: RangeBounds<K> + Sync, T: Clone + Send + Sync, F: Fn(T,&K,&P,&V) -> T + Sync, R: Fn(T,T) -> T + Sync {
+        let levels = match rayon::current_num_threads() {
+            1 => 0,
+            n => n.next_power_of_two().trailing_zeros() + LEVELS,
+        };
+        let fold = Fold { range, init, fold, reduce };
+        fold.node(&self.index,self.root,levels,fold.init.clone()).map_err(Error::Index)
+    }
+}
+
+struct Fold<B,T,F,R> {
+    range: B,
+    init: T,
+    fold: F,
+    reduce: R,
+}
+impl<B,T: Clone + Send + Sync,F,R> Fold<B,T,F,R> {
+    fn node<K: PartialOrd + Sync,P: Sync,V: Sync,A: Sync>(&self, index: &Index<K,P,V,A>, node: NodePtr, levels: u32, acc: T) -> Result<T,IndexError>
+    where B: RangeBounds<K> + Sync, F: Fn(T,&K,&P,&V) -> T + Sync, R: Fn(T,T) -> T + Sync {
+        if node.is_none() { return Ok(acc); }
+        let entry = index.get(&node)?;
+        if !after_start(&entry.key,self.range.start_bound()) { return self.node(index,entry.right,levels,acc); }
+        if !before_end(&entry.key,self.range.end_bound()) { return self.node(index,entry.left,levels,acc); }
+        if levels == 0 {
+            let acc = self.node(index,entry.left,0,acc)?;
+            let acc = (self.fold)(acc,&entry.key,&entry.priority,&entry.value);
+            return self.node(index,entry.right,0,acc);
+        }
+        let (left,right) = rayon::join(|| self.node(index,entry.left,levels - 1,acc),|| self.node(index,entry.right,levels - 1,self.init.clone()));
+        let acc = (self.fold)(left?,&entry.key,&entry.priority,&entry.value);
+        Ok((self.reduce)(acc,right?))
+    }
+}
+
+// Arena slots shared by union tasks. The tasks work on disjoint subtrees, so in an intact
+// arena no node is reached from two of them; all the same, every access claims its slot
+// for as long as it holds the node, so a link back to a node in hand or into the other
+// task's part is an `IndexError` rather than a second `&mut`. Freeing slots is left
+// until all tasks are done.
+struct Slots<'t,N> {
+    ptr: *mut Option<N>,
+    claimed: Vec<AtomicBool>,
+    slots: PhantomData<&'t mut [Option<N>]>,
+}
+// SAFETY: a node is only reached through a `Claim`, which excludes every other one to its slot
+unsafe impl<'t,N: Send> Sync for Slots<'t,N> {}
+impl<'t,N> Slots<'t,N> {
+    fn new(slots: &'t mut [Option<N>]) -> Slots<'t,N> {
+        Slots { ptr: slots.as_mut_ptr(), claimed: slots.iter().map(|_| AtomicBool::new(false)).collect(), slots: PhantomData }
+    }
+    fn get(&self, id: NodePtr) -> Result<Claim<'_,'t,N>,IndexError> {
+        let id = id.ok_or(IndexError::None)?;
+        let claimed = self.claimed.get(id).ok_or(IndexError::OutOfBounds(id))?;
+        if claimed.swap(true,AtomicOrdering::Acquire) { return Err(IndexError::Shared(id)); }
+        let claim = Claim { slots: self, id }; // released on the way out, found or not
+        // SAFETY: in bounds, and claimed, so nothing else refers to the slot
+        match unsafe { &*self.ptr.add(id) } {
+            Some(_) => Ok(claim),
+            None => Err(IndexError::Empty(id)),
+        }
+    }
+}
+struct Claim<'s,'t,N> {
+    slots: &'s Slots<'t,N>,
+    id: Id,
+}
+impl<'s,'t,N> Deref for Claim<'s,'t,N> {
+    type Target = N;
+    fn deref(&self) -> &N {
+        // SAFETY: claimed, and found occupied when it was
+        unsafe { (*self.slots.ptr.add(self.id)).as_ref().unwrap_unchecked() }
+    }
+}
+impl<'s,'t,N> DerefMut for Claim<'s,'t,N> {
+    fn deref_mut(&mut self) -> &mut N {
+        // SAFETY: as for `deref`
+        unsafe { (*self.slots.ptr.add(self.id)).as_mut().unwrap_unchecked() }
+    }
+}
+impl<'s,'t,N> Drop for Claim<'s,'t,N> {
+    fn drop(&mut self) {
+        self.slots.claimed[self.id].store(false,AtomicOrdering::Release);
+    }
+}
+
+fn par_union_nodes<K: PartialOrd + Send,P: PartialOrd + Send,V: Send,A: Aggregate<V> + Send,H: HeapPolicy>(slots: &Slots<'_,Node<K,P,V,A>>, a: NodePtr, b: NodePtr, flipped: bool, levels: u32, dropped: &mut Vec<Id>) -> Result<NodePtr,IndexError> {
+    if a.is_none() { return Ok(b); }
+    if b.is_none() { return Ok(a); }
+    let below = {
+        let (a,b) = (slots.get(a)?,slots.get(b)?);
+        match H::rank((&a.key,&a.priority),(&b.key,&b.priority)) {
+            Some(Ordering::Less) => true,
+            Some(Ordering::Equal) => flipped, // as in `union_nodes`
+            _ => false,
+        }
+    };
+    let (a,b,flipped) = match below {
+        true => (b,a,!flipped),
+        false => (a,b,flipped),
+    };
+    let (l,e,r,al,ar) = {
+        let node = slots.get(a)?;
+        let (l,e,r) = split_slots(slots,b,&node.key)?;
+        (l,e,r,node.left,node.right)
+    };
+    let (left,right) = match levels {
+        0 => (par_union_nodes::<K,P,V,A,H>(slots,al,l,flipped,0,dropped)?,par_union_nodes::<K,P,V,A,H>(slots,ar,r,flipped,0,dropped)?),
+        _ => {
+            let mut dropped_right = Vec::new();
+            let (left,right) = rayon::join(|| par_union_nodes::<K,P,V,A,H>(slots,al,l,flipped,levels - 1,dropped),|| par_union_nodes::<K,P,V,A,H>(slots,ar,r,flipped,levels - 1,&mut dropped_right));
+            dropped.extend(dropped_right);
+            (left?,right?)
+        },
+    };
+    {
+        let mut node = slots.get(a)?;
+        node.left = left;
+        node.right = right;
+    }
+    update_slots(slots,a)?;
+    if let Some(e) = e { dropped.push(e); } // lower or equal priority than `a`
+    Ok(a)
+}
+fn split_slots<K: PartialOrd,P,V,A: Aggregate<V>>(slots: &Slots<'_,Node<K,P,V,A>>, node: NodePtr, key: &K) -> Result<(NodePtr,NodePtr,NodePtr),IndexError> { // left, entry, right
+    if node.is_none() { return Ok((None,None,None)); }
+    let mut entry = slots.get(node)?;
+    if entry.key == *key {
+        let (l,r) = (entry.left.take(),entry.right.take());
+        entry.agg = A::from_value(&entry.value);
+        Ok((l,node,r))
+    } else if entry.key > *key {
+        let (l,e,r) = split_slots(slots,entry.left,key)?;
+        entry.left = r;
+        drop(entry);
+        update_slots(slots,node)?;
+        Ok((l,e,node))
+    } else {
+        let (l,e,r) = split_slots(slots,entry.right,key)?;
+        entry.right = l;
+        drop(entry);
+        update_slots(slots,node)?;
+        Ok((node,e,r))
+    }
+}
+fn update_slots<K,P,V,A: Aggregate<V>>(slots: &Slots<'_,Node<K,P,V,A>>, node: NodePtr) -> Result<(),IndexError> {
+    let mut entry = slots.get(node)?;
+    let mut agg = A::from_value(&entry.value);
+    if entry.left.is_some() { agg = A::combine(&slots.get(entry.left)?.agg,&agg); }
+    if entry.right.is_some() { agg = A::combine(&agg,&slots.get(entry.right)?.agg); }
+    entry.agg = agg;
+    Ok(())
+}
+
+impl<K: Send,P: Send,V: Send,A: Send,H> IntoParallelIterator for Treap<K,P,V,A,H> {
+    type Item = (K,P,V);
+    type Iter = IntoParIter<K,P,V,A>;
+
+    fn into_par_iter(self) -> IntoParIter<K,P,V,A> {
+        IntoParIter { nodes: self.index.index }
+    }
+}
+
+pub struct IntoParIter<K,P,V,A = ()> {
+    nodes: Vec<Option<Node<K,P,V,A>>>,
+}
+impl<K: Send,P: Send,V: Send,A: Send> ParallelIterator for IntoParIter<K,P,V,A> {
+    type Item = (K,P,V);
+
+    fn drive_unindexed<C: UnindexedConsumer<Self::Item>>(self, consumer: C) -> C::Result {
+        self.nodes.into_par_iter().filter_map(|node| node.map(|node| (node.key,node.priority,node.value))).drive_unindexed(consumer)
+    }
+}
@@ -0,0 +1,277 @@
+use core::ops::RangeBounds;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+use crate::iter::{after_start,before_end};
+
+type Link<K,P,V> = Option<Arc<Node<K,P,V>>>;
+type Parts<K,P,V> = (Link<K,P,V>,Link<K,P,V>,Link<K,P,V>); // left, entry, right
+
+#[derive(Debug,Clone)]
+struct Node<K,P,V> {
+    key: K,
+    priority: P,
+    value: V,
+    left: Link<K,P,V>,
+    right: Link<K,P,V>,
+}
+
+/// Treap with `Arc`-shared nodes. Every version stays valid: an update copies only the
+/// O(log n) nodes on its path and shares the rest with the version it came from.
+#[derive(Debug)]
+pub struct PersistentTreap<K,P,V> {
+    root: Link<K,P,V>,
+    len: usize,
+}
+impl<K,P,V> Clone for PersistentTreap<K,P,V> {
+    fn clone(&self) -> PersistentTreap<K,P,V> {
+        PersistentTreap { root: self.root.clone(), len: self.len }
+    }
+}
+impl<K,P,V> Default for PersistentTreap<K,P,V> {
+    fn default() -> PersistentTreap<K,P,V> {
+        PersistentTreap::new()
+    }
+}
+impl<K,P,V> PersistentTreap<K,P,V> {
+    pub fn new() -> PersistentTreap<K,P,V> {
+        PersistentTreap { root: None, len: 0 }
+    }
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+    /// Whether both versions share the same root, i.e. are known to be identical.
+    pub fn ptr_eq(&self, other: &PersistentTreap<K,P,V>) -> bool {
+        match (&self.root,&other.root) {
+            (Some(a),Some(b)) => Arc::ptr_eq(a,b),
+            (None,None) => true,
+            _ => false,
+        }
+    }
+    pub fn peek(&self) -> Option<(&K,&P,&V)> {
+        self.root.as_ref().map(|node| (&node.key,&node.priority,&node.value))
+    }
+    pub fn iter(&self) -> Iter<'_,K,P,V> {
+        let mut iter = Iter { stack: Vec::new() };
+        iter.push_left(self.root.as_deref());
+        iter
+    }
+}
+impl<K: PartialOrd,P,V> PersistentTreap<K,P,V> {
+    pub fn get(&self, key: &K) -> Option<(&P,&V)> {
+        let mut node = self.root.as_deref();
+        while let Some(entry) = node {
+            if entry.key == *key { return Some((&entry.priority,&entry.value)); }
+            node = if entry.key > *key { entry.left.as_deref() } else { entry.right.as_deref() };
+        }
+        None
+    }
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+    pub fn range<B: RangeBounds<K>>(&self, range: B) -> Range<'_,K,P,V> {
+        let mut iter = Iter { stack: Vec::new() };
+        let mut node = self.root.as_deref();
+        while let Some(entry) = node {
+            if after_start(&entry.key,range.start_bound()) {
+                iter.stack.push(entry);
+                node = entry.left.as_deref();
+            } else {
+                node = entry.right.as_deref();
+            }
+        }
+        let mut last = None;
+        let mut node = self.root.as_deref();
+        while let Some(entry) = node {
+            if before_end(&entry.key,range.end_bound()) {
+                last = Some(entry);
+                node = entry.right.as_deref();
+            } else {
+                node = entry.left.as_deref();
+            }
+        }
+        let empty = match (last,iter.stack.last()) {
+            (Some(_),Some(first)) => !before_end(&first.key,range.end_bound()),
+            _ => true,
+        };
+        if empty { iter.stack.clear(); }
+        Range { iter, last }
+    }
+}
+impl<K: PartialOrd + Clone,P: PartialOrd + Clone,V: Clone> PersistentTreap<K,P,V> {
+    /// New version with `key` set; `self` is left as it was.
+    pub fn insert(&self, key: K, priority: P, value: V) -> PersistentTreap<K,P,V> {
+        let mut version = self.clone();
+        version.insert_mut(key,priority,value);
+        version
+    }
+    /// New version without `key`; `self` is left as it was.
+    pub fn remove(&self, key: &K) -> PersistentTreap<K,P,V> {
+        let mut version = self.clone();
+        version.remove_mut(key);
+        version
+    }
+    // in place, copying only nodes still shared with other versions
+    pub(crate) fn insert_mut(&mut self, key: K, priority: P, value: V) -> Option<(P,V)> {
+        let (l,e,r) = split(self.root.take(),&key);
+        let old = e.map(|e| {
+            let node = Arc::try_unwrap(e).unwrap_or_else(|e| (*e).clone());
+            (node.priority,node.value)
+        });
+        if old.is_none() { self.len += 1; }
+        let new = Some(Arc::new(Node { key, priority, value, left: None, right: None }));
+        self.root = merge(merge(l,new),r);
+        old
+    }
+    pub(crate) fn remove_mut(&mut self, key: &K) -> Option<(P,V)> {
+        let (l,e,r) = split(self.root.take(),key);
+        self.root = merge(l,r);
+        e.map(|e| {
+            self.len -= 1;
+            let node = Arc::try_unwrap(e).unwrap_or_else(|e| (*e).clone());
+            (node.priority,node.value)
+        })
+    }
+    pub(crate) fn get_mut(&mut self, key: &K) -> Option<(&P,&mut V)> {
+        if !self.contains_key(key) { return None; } // don't copy a path for nothing
+        let mut link = &mut self.root;
+        loop {
+            let node = Arc::make_mut(link.as_mut()?);
+            if node.key == *key { return Some((&node.priority,&mut node.value)); }
+            link = if node.key > *key { &mut node.left } else { &mut node.right };
+        }
+    }
+}
+
+impl<'t,K,P,V> IntoIterator for &'t PersistentTreap<K,P,V> {
+    type Item = (&'t K, &'t P, &'t V);
+    type IntoIter = Iter<'t,K,P,V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct Iter<'t,K,P,V> {
+    stack: Vec<&'t Node<K,P,V>>,
+}
+impl<'t,K,P,V> Iter<'t,K,P,V> {
+    fn push_left(&mut self, mut node: Option<&'t Node<K,P,V>>) {
+        while let Some(entry) = node {
+            self.stack.push(entry);
+            node = entry.left.as_deref();
+        }
+    }
+}
+impl<'t,K,P,V> Iterator for Iter<'t,K,P,V> {
+    type Item = (&'t K, &'t P, &'t V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let entry = self.stack.pop()?;
+        self.push_left(entry.right.as_deref());
+        Some((&entry.key,&entry.priority,&entry.value))
+    }
+}
+
+pub struct Range<'t,K,P,V> {
+    iter: Iter<'t,K,P,V>,
+    last: Option<&'t Node<K,P,V>>,
+}
+impl<'t,K,P,V> Iterator for Range<'t,K,P,V> {
+    type Item = (&'t K, &'t P, &'t V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let entry = self.iter.stack.pop()?;
+        self.iter.push_left(entry.right.as_deref());
+        if let Some(last) = self.last {
+            if core::ptr::eq(entry,last) { self.iter.stack.clear(); }
+        }
+        Some((&entry.key,&entry.priority,&entry.value))
+    }
+}
+
+fn split<K: PartialOrd + Clone,P: Clone,V: Clone>(link: Link<K,P,V>, key: &K) -> Parts<K,P,V> {
+    let mut node = match link {
+        Some(node) => node,
+        None => return (None,None,None),
+    };
+    if node.key == *key {
+        let (l,r) = match Arc::get_mut(&mut node) {
+            Some(entry) => (entry.left.take(),entry.right.take()),
+            None => (node.left.clone(),node.right.clone()),
+        };
+        return (l,Some(node),r);
+    }
+    let entry = Arc::make_mut(&mut node);
+    if entry.key > *key {
+        let (l,e,r) = split(entry.left.take(),key);
+        entry.left = r;
+        (l,e,Some(node))
+    } else {
+        let (l,e,r) = split(entry.right.take(),key);
+        entry.right = l;
+        (Some(node),e,r)
+    }
+}
+fn merge<K: Clone,P: PartialOrd + Clone,V: Clone>(left: Link<K,P,V>, right: Link<K,P,V>) -> Link<K,P,V> {
+    match (left,right) {
+        (None,right) => right,
+        (left,None) => left,
+        (Some(mut left),Some(mut right)) => {
+            if left.priority > right.priority {
+                let entry = Arc::make_mut(&mut left);
+                entry.right = merge(entry.right.take(),Some(right));
+                Some(left)
+            } else {
+                let entry = Arc::make_mut(&mut right);
+                entry.left = merge(Some(left),entry.left.take());
+                Some(right)
+            }
+        },
+    }
+}
+
+/// One entry of `PersistentTreap::diff`.
+#[derive(Debug,Clone,PartialEq)]
+pub enum Change<K,P,V> {
+    Inserted(K,P,V),
+    Removed(K,P,V),
+    Changed(K,(P,V),(P,V)), // key, old, new
+}
+
+impl<K: PartialOrd + Clone,P: PartialOrd + Clone,V: PartialEq + Clone> PersistentTreap<K,P,V> {
+    /// Changes turning `self` into `other`, in key order. Subtrees the two versions still
+    /// share are skipped, so diffing versions a few updates apart is cheap.
+    pub fn diff(&self, other: &PersistentTreap<K,P,V>) -> Vec<Change<K,P,V>> {
+        let mut changes = Vec::new();
+        diff_nodes(self.root.clone(),other.root.clone(),&mut changes);
+        changes
+    }
+}
+
+fn diff_nodes<K: PartialOrd + Clone,P: PartialOrd + Clone,V: PartialEq + Clone>(old: Link<K,P,V>, new: Link<K,P,V>, changes: &mut Vec<Change<K,P,V>>) {
+    let old = match old {
+        Some(old) => old,
+        None => {
+            let mut iter = Iter { stack: Vec::new() };
+            iter.push_left(new.as_deref());
+            changes.extend(iter.map(|(k,p,v)| Change::Inserted(k.clone(),p.clone(),v.clone())));
+            return;
+        },
+    };
+    if let Some(new) = &new {
+        if Arc::ptr_eq(&old,new) { return; }
+    }
+    let (l,e,r) = split(new,&old.key);
+    diff_nodes(old.left.clone(),l,changes);
+    match e {
+        None => changes.push(Change::Removed(old.key.clone(),old.priority.clone(),old.value.clone())),
+        Some(e) => if e.priority != old.priority || e.value != old.value {
+            changes.push(Change::Changed(old.key.clone(),(old.priority.clone(),old.value.clone()),(e.priority.clone(),e.value.clone())));
+        },
+    }
+    diff_nodes(old.right.clone(),r,changes);
+}
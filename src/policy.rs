@@ -0,0 +1,92 @@
+use core::cmp::Ordering;
+use core::marker::PhantomData;
+
+/// Which end of the priority order sits at the root of a `Treap`. `MaxPolicy`, the
+/// default, keeps the highest priority on top; `MinPolicy` the lowest.
+///
+/// Entries with equal priorities are ordered by `tie_break`, so the shape of a treap
+/// depends only on its entries, never on the order of operations that built it.
+pub trait HeapPolicy {
+    /// Rank of `a` against `b`: `Greater` if `a` belongs nearer the root.
+    fn compare<P: PartialOrd>(a: &P, b: &P) -> Option<Ordering>;
+    /// Rank between keys of equal priority; the larger key goes on top by default.
+    fn tie_break<K: PartialOrd>(a: &K, b: &K) -> Option<Ordering> {
+        a.partial_cmp(b)
+    }
+
+    fn above<P: PartialOrd>(a: &P, b: &P) -> bool {
+        Self::compare(a,b) == Some(Ordering::Greater)
+    }
+    fn below<P: PartialOrd>(a: &P, b: &P) -> bool {
+        Self::compare(a,b) == Some(Ordering::Less)
+    }
+    /// Rank of entry `a` against entry `b`, ties broken.
+    fn rank<K: PartialOrd,P: PartialOrd>(a: (&K,&P), b: (&K,&P)) -> Option<Ordering> {
+        match Self::compare(a.1,b.1) {
+            Some(Ordering::Equal) => Self::tie_break(a.0,b.0),
+            order => order,
+        }
+    }
+}
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Default)]
+pub struct MaxPolicy;
+impl HeapPolicy for MaxPolicy {
+    fn compare<P: PartialOrd>(a: &P, b: &P) -> Option<Ordering> {
+        a.partial_cmp(b)
+    }
+}
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Default)]
+pub struct MinPolicy;
+impl HeapPolicy for MinPolicy {
+    fn compare<P: PartialOrd>(a: &P, b: &P) -> Option<Ordering> {
+        b.partial_cmp(a)
+    }
+}
+
+/// `H` with ties going to the smaller key instead.
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Default)]
+pub struct ReverseTies<H>(PhantomData<H>);
+impl<H: HeapPolicy> HeapPolicy for ReverseTies<H> {
+    fn compare<P: PartialOrd>(a: &P, b: &P) -> Option<Ordering> {
+        H::compare(a,b)
+    }
+    fn tie_break<K: PartialOrd>(a: &K, b: &K) -> Option<Ordering> {
+        H::tie_break(b,a)
+    }
+}
+
+/// Float priority ordered by `total_cmp` (IEEE 754 totalOrder): NaNs get a fixed place,
+/// above every number if positive and below if negative, instead of comparing unordered
+/// with everything and quietly breaking heap order. `-0.0` ranks below `0.0`.
+#[derive(Debug,Clone,Copy,Default)]
+pub struct TotalOrder<F>(pub F);
+
+macro_rules! total_order {
+    ($float:ty) => {
+        impl PartialEq for TotalOrder<$float> {
+            fn eq(&self, other: &TotalOrder<$float>) -> bool {
+                self.cmp(other) == Ordering::Equal
+            }
+        }
+        impl Eq for TotalOrder<$float> {}
+        impl PartialOrd for TotalOrder<$float> {
+            fn partial_cmp(&self, other: &TotalOrder<$float>) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+        impl Ord for TotalOrder<$float> {
+            fn cmp(&self, other: &TotalOrder<$float>) -> Ordering {
+                self.0.total_cmp(&other.0)
+            }
+        }
+        impl From<$float> for TotalOrder<$float> {
+            fn from(value: $float) -> TotalOrder<$float> {
+                TotalOrder(value)
+            }
+        }
+    };
+}
+total_order!(f32);
+total_order!(f64);
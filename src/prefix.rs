@@ -0,0 +1,28 @@
+use core::cmp::Ordering;
+
+/// Keys whose leading part can be searched on its own, like a tuple by its first fields.
+/// The keys sharing a prefix form one run in key order, so `Treap::range_prefix` finds
+/// them the way `range` finds a key range.
+pub trait Prefix<Q> {
+    /// How the leading part of this key compares with `prefix`.
+    fn cmp_prefix(&self, prefix: &Q) -> Option<Ordering>;
+}
+
+impl<A: PartialOrd,B> Prefix<A> for (A,B) {
+    fn cmp_prefix(&self, prefix: &A) -> Option<Ordering> {
+        self.0.partial_cmp(prefix)
+    }
+}
+impl<A: PartialOrd,B,C> Prefix<A> for (A,B,C) {
+    fn cmp_prefix(&self, prefix: &A) -> Option<Ordering> {
+        self.0.partial_cmp(prefix)
+    }
+}
+impl<A: PartialOrd,B: PartialOrd,C> Prefix<(A,B)> for (A,B,C) {
+    fn cmp_prefix(&self, prefix: &(A,B)) -> Option<Ordering> {
+        match self.0.partial_cmp(&prefix.0) {
+            Some(Ordering::Equal) => self.1.partial_cmp(&prefix.1),
+            order => order,
+        }
+    }
+}
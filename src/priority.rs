@@ -0,0 +1,85 @@
+use core::hash::{Hash,Hasher};
+#[cfg(feature = "std")]
+use core::sync::atomic::AtomicUsize;
+
+use rand::RngCore;
+#[cfg(feature = "std")]
+use rand::SeedableRng;
+#[cfg(feature = "std")]
+use rand::rngs::{StdRng,OsRng};
+use siphasher::sip::SipHasher13;
+
+#[cfg(feature = "std")]
+use crate::bump;
+
+/// Source of priorities for the auto-priority wrappers (`TreapMap`, `TreapSet`).
+pub trait PrioritySource<K> {
+    fn priority(&mut self, key: &K) -> u64;
+}
+impl<K,R: RngCore> PrioritySource<K> for R {
+    fn priority(&mut self, _key: &K) -> u64 {
+        self.next_u64()
+    }
+}
+
+// Seeds the RNG of a wrapper made with `new()`. Where the platform has no entropy to
+// give, as on wasm32-unknown-unknown without the `js` feature, this falls back to a seed
+// from a counter instead of panicking: the tree stays balanced on average, but its
+// priorities become predictable. The counter goes through `bump`, as std targets such
+// as 32-bit mips and powerpc have no 64-bit atomics.
+#[cfg(feature = "std")]
+pub(crate) fn entropy_rng() -> StdRng {
+    static FALLBACK: AtomicUsize = AtomicUsize::new(0);
+    StdRng::from_rng(OsRng).unwrap_or_else(|_| {
+        let n = bump(&FALLBACK) as u64;
+        StdRng::seed_from_u64(0x5452_4541_5053u64.wrapping_add(n.wrapping_mul(0x9e37_79b9_7f4a_7c15)))
+    })
+}
+
+/// Priority = SipHash-1-3(seed, key): the treap shape depends only on the set of keys,
+/// not on the order they were inserted in.
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub struct KeyHash {
+    seed: u64,
+}
+impl KeyHash {
+    pub fn new(seed: u64) -> KeyHash {
+        KeyHash { seed }
+    }
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+impl<K: Hash> PrioritySource<K> for KeyHash {
+    fn priority(&mut self, key: &K) -> u64 {
+        let mut hasher = StableHasher(SipHasher13::new_with_keys(self.seed,0));
+        key.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+// integers are fed as little-endian u64-widened bytes, so replicas agree across platforms
+struct StableHasher(SipHasher13);
+impl Hasher for StableHasher {
+    fn finish(&self) -> u64 {
+        self.0.finish()
+    }
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.write(bytes)
+    }
+    fn write_u16(&mut self, i: u16) {
+        self.0.write(&i.to_le_bytes())
+    }
+    fn write_u32(&mut self, i: u32) {
+        self.0.write(&i.to_le_bytes())
+    }
+    fn write_u64(&mut self, i: u64) {
+        self.0.write(&i.to_le_bytes())
+    }
+    fn write_u128(&mut self, i: u128) {
+        self.0.write(&i.to_le_bytes())
+    }
+    fn write_usize(&mut self, i: usize) {
+        self.0.write(&(i as u64).to_le_bytes())
+    }
+}
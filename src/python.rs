@@ -0,0 +1,156 @@
+// Python classes `treap.Treap` and `treap.TreapMap`, built as an extension module with
+// `maturin build --features python` or `cargo rustc --release --features python --crate-type cdylib`.
+
+use core::cmp::Ordering;
+use alloc::format;
+use alloc::vec::Vec;
+
+use pyo3::prelude::*;
+use pyo3::exceptions::{PyKeyError,PyIndexError,PyRuntimeError};
+use pyo3::types::{PyIterator,PyList};
+
+use crate::{Treap,TreapMap,TotalOrder,Error};
+
+// Python object ordered by Python's own comparisons; keys that don't compare, or whose
+// comparison raises, are unordered, so they are never found.
+struct PyKey(PyObject);
+impl PartialEq for PyKey {
+    fn eq(&self, other: &PyKey) -> bool {
+        Python::with_gil(|py| self.0.bind(py).eq(other.0.bind(py)).unwrap_or(false))
+    }
+}
+impl PartialOrd for PyKey {
+    fn partial_cmp(&self, other: &PyKey) -> Option<Ordering> {
+        Python::with_gil(|py| self.0.bind(py).compare(other.0.bind(py)).ok())
+    }
+}
+
+fn raise(e: Error) -> PyErr {
+    PyRuntimeError::new_err(format!("corrupted treap: {:?}",e))
+}
+
+/// `Treap` of any mutually comparable keys, float priorities and any values: a dict in key
+/// order and a max-heap on priority at once.
+#[pyclass(name = "Treap", module = "treap")]
+pub struct PyTreap {
+    treap: Treap<PyKey,TotalOrder<f64>,PyObject>,
+}
+
+#[pymethods]
+impl PyTreap {
+    #[new]
+    fn new() -> PyTreap {
+        PyTreap { treap: Treap::new() }
+    }
+    fn __len__(&self) -> usize {
+        self.treap.len()
+    }
+    fn __contains__(&self, key: PyObject) -> PyResult<bool> {
+        Ok(self.treap.get(&PyKey(key)).map_err(raise)?.is_some())
+    }
+    fn __getitem__(&self, py: Python<'_>, key: PyObject) -> PyResult<PyObject> {
+        match self.treap.get(&PyKey(key.clone_ref(py))).map_err(raise)? {
+            Some((_,value)) => Ok(value.clone_ref(py)),
+            None => Err(PyKeyError::new_err(key)),
+        }
+    }
+    fn __delitem__(&mut self, py: Python<'_>, key: PyObject) -> PyResult<()> {
+        match self.treap.remove(&PyKey(key.clone_ref(py))).map_err(raise)? {
+            Some(_) => Ok(()),
+            None => Err(PyKeyError::new_err(key)),
+        }
+    }
+    fn __iter__(&self, py: Python<'_>) -> PyResult<Py<PyIterator>> {
+        Ok(PyList::new(py,self.keys(py))?.try_iter()?.unbind())
+    }
+    #[pyo3(signature = (key, default = None))]
+    fn get(&self, py: Python<'_>, key: PyObject, default: Option<PyObject>) -> PyResult<Option<PyObject>> {
+        Ok(match self.treap.get(&PyKey(key)).map_err(raise)? {
+            Some((_,value)) => Some(value.clone_ref(py)),
+            None => default,
+        })
+    }
+    fn priority(&self, key: PyObject) -> PyResult<Option<f64>> {
+        Ok(self.treap.priority(&PyKey(key)).map_err(raise)?.map(|p| p.0))
+    }
+    /// Inserts or replaces the entry of `key`, returning the old `(priority, value)`.
+    fn insert(&mut self, key: PyObject, priority: f64, value: PyObject) -> PyResult<Option<(f64,PyObject)>> {
+        Ok(self.treap.insert(PyKey(key),TotalOrder(priority),value).map_err(raise)?.map(|(p,v)| (p.0,v)))
+    }
+    /// Removes and returns the `(key, priority, value)` with the highest priority.
+    fn pop(&mut self) -> PyResult<(PyObject,f64,PyObject)> {
+        match self.treap.pop().map_err(raise)? {
+            Some((key,priority,value)) => Ok((key.0,priority.0,value)),
+            None => Err(PyIndexError::new_err("pop from an empty treap")),
+        }
+    }
+    fn peek(&self, py: Python<'_>) -> PyResult<Option<(PyObject,f64,PyObject)>> {
+        Ok(self.treap.peek().map_err(raise)?.map(|(key,priority,value)| (key.0.clone_ref(py),priority.0,value.clone_ref(py))))
+    }
+    fn keys(&self, py: Python<'_>) -> Vec<PyObject> {
+        self.treap.iter().map(|(key,_,_)| key.0.clone_ref(py)).collect()
+    }
+    /// `(key, priority, value)` triples in key order.
+    fn items(&self, py: Python<'_>) -> Vec<(PyObject,f64,PyObject)> {
+        self.treap.iter().map(|(key,priority,value)| (key.0.clone_ref(py),priority.0,value.clone_ref(py))).collect()
+    }
+}
+
+/// `TreapMap` of any mutually comparable keys: a dict kept in key order.
+#[pyclass(name = "TreapMap", module = "treap")]
+pub struct PyTreapMap {
+    map: TreapMap<PyKey,PyObject>,
+}
+
+#[pymethods]
+impl PyTreapMap {
+    #[new]
+    fn new() -> PyTreapMap {
+        PyTreapMap { map: TreapMap::new() }
+    }
+    fn __len__(&self) -> usize {
+        self.map.len()
+    }
+    fn __contains__(&self, key: PyObject) -> PyResult<bool> {
+        self.map.contains_key(&PyKey(key)).map_err(raise)
+    }
+    fn __getitem__(&self, py: Python<'_>, key: PyObject) -> PyResult<PyObject> {
+        match self.map.get(&PyKey(key.clone_ref(py))).map_err(raise)? {
+            Some(value) => Ok(value.clone_ref(py)),
+            None => Err(PyKeyError::new_err(key)),
+        }
+    }
+    fn __setitem__(&mut self, key: PyObject, value: PyObject) -> PyResult<()> {
+        self.map.insert(PyKey(key),value).map_err(raise)?;
+        Ok(())
+    }
+    fn __delitem__(&mut self, py: Python<'_>, key: PyObject) -> PyResult<()> {
+        match self.map.remove(&PyKey(key.clone_ref(py))).map_err(raise)? {
+            Some(_) => Ok(()),
+            None => Err(PyKeyError::new_err(key)),
+        }
+    }
+    fn __iter__(&self, py: Python<'_>) -> PyResult<Py<PyIterator>> {
+        Ok(PyList::new(py,self.keys(py))?.try_iter()?.unbind())
+    }
+    #[pyo3(signature = (key, default = None))]
+    fn get(&self, py: Python<'_>, key: PyObject, default: Option<PyObject>) -> PyResult<Option<PyObject>> {
+        Ok(match self.map.get(&PyKey(key)).map_err(raise)? {
+            Some(value) => Some(value.clone_ref(py)),
+            None => default,
+        })
+    }
+    fn keys(&self, py: Python<'_>) -> Vec<PyObject> {
+        self.map.iter().map(|(key,_)| key.0.clone_ref(py)).collect()
+    }
+    fn items(&self, py: Python<'_>) -> Vec<(PyObject,PyObject)> {
+        self.map.iter().map(|(key,value)| (key.0.clone_ref(py),value.clone_ref(py))).collect()
+    }
+}
+
+#[pymodule]
+fn treap(m: &Bound<'_,PyModule>) -> PyResult<()> {
+    m.add_class::<PyTreap>()?;
+    m.add_class::<PyTreapMap>()?;
+    Ok(())
+}
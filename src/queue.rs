@@ -0,0 +1,48 @@
+use crate::{Treap,Error};
+
+/// Addressable priority queue: items are unique keys, the highest priority is served first.
+#[derive(Debug)]
+pub struct TreapQueue<K,P> {
+    treap: Treap<K,P,()>,
+}
+impl<K: PartialOrd,P: PartialOrd> Default for TreapQueue<K,P> {
+    fn default() -> TreapQueue<K,P> {
+        TreapQueue::new()
+    }
+}
+impl<K: PartialOrd,P: PartialOrd> TreapQueue<K,P> {
+    pub fn new() -> TreapQueue<K,P> {
+        TreapQueue { treap: Treap::new() }
+    }
+    pub fn len(&self) -> usize {
+        self.treap.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.treap.is_empty()
+    }
+    pub fn push(&mut self, item: K, priority: P) -> Result<Option<P>,Error> {
+        Ok(self.treap.insert(item,priority,())?.map(|(p,_)| p))
+    }
+    pub fn pop(&mut self) -> Result<Option<(K,P)>,Error> {
+        Ok(self.treap.pop()?.map(|(k,p,_)| (k,p)))
+    }
+    pub fn peek(&self) -> Result<Option<(&K,&P)>,Error> {
+        Ok(self.treap.peek()?.map(|(k,p,_)| (k,p)))
+    }
+    pub fn priority(&self, item: &K) -> Result<Option<&P>,Error> {
+        self.treap.priority(item)
+    }
+    pub fn contains(&self, item: &K) -> Result<bool,Error> {
+        Ok(self.treap.priority(item)?.is_some())
+    }
+    pub fn change_priority(&mut self, item: &K, priority: P) -> Result<Option<P>,Error> {
+        self.treap.prioritize(item,priority)
+    }
+    pub fn remove(&mut self, item: &K) -> Result<Option<P>,Error> {
+        Ok(self.treap.remove(item)?.map(|(p,_)| p))
+    }
+    /// Moves every item of `other` in; an item queued in both keeps its higher priority.
+    pub fn meld(self, other: TreapQueue<K,P>) -> Result<TreapQueue<K,P>,Error> {
+        Ok(TreapQueue { treap: self.treap.meld(other.treap)? })
+    }
+}
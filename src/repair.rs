@@ -0,0 +1,174 @@
+use core::mem;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::{Treap,Index,Id,NodePtr,Aggregate,HeapPolicy};
+
+/// What `Treap::repair` kept and dropped.
+#[derive(Debug,Default,Clone,Copy,PartialEq,Eq)]
+pub struct RepairReport {
+    /// Entries in the rebuilt treap.
+    pub salvaged: usize,
+    /// Nodes in the arena that no path from the root led to.
+    pub unreachable: usize,
+    /// Reached nodes dropped to leave the rest in key order: misplaced or repeated keys.
+    pub out_of_order: usize,
+    /// Links to free or out-of-range slots, or back to a node already reached; cut.
+    pub dangling: usize,
+}
+
+impl<K: PartialOrd,P: PartialOrd,V,A: Aggregate<V>,H: HeapPolicy> Treap<K,P,V,A,H> {
+    /// Way back from an `Error::Index`: walks the tree from the root without trusting any
+    /// link, keeps as many of the entries it reaches as are in key order, and rebuilds the
+    /// treap from them in a new arena (so `SyncHandle`s don't carry over); heap order and
+    /// aggregates come out right by construction. On an intact treap only the arena
+    /// changes.
+    pub fn repair(&mut self) -> RepairReport {
+        let mut report = RepairReport::default();
+        let mut index: Index<K,P,V,A> = mem::replace(&mut self.index,Index::new());
+        let root = self.root.take();
+
+        // in-order walk; every node is entered at most once, so cycles end it
+        let mut seen = vec![false; index.index.len()];
+        let mut enter = |node: NodePtr, index: &Index<K,P,V,A>, stack: &mut Vec<(Id,bool)>, report: &mut RepairReport| {
+            if let Some(id) = node {
+                match index.get(&node) {
+                    Ok(_) if !seen[id] => {
+                        seen[id] = true;
+                        stack.push((id,false));
+                    },
+                    _ => report.dangling += 1,
+                }
+            }
+        };
+        let mut order = Vec::new();
+        let mut stack = Vec::new(); // node, whether its left side is done
+        enter(root,&index,&mut stack,&mut report);
+        while let Some((id,left_done)) = stack.pop() {
+            let (left,right) = match index.get(&Some(id)) {
+                Ok(entry) => (entry.left,entry.right),
+                Err(_) => continue,
+            };
+            if left_done {
+                order.push(id);
+                enter(right,&index,&mut stack,&mut report);
+            } else {
+                stack.push((id,true));
+                enter(left,&index,&mut stack,&mut report);
+            }
+        }
+        report.unreachable = index.index.iter().filter(|slot| slot.is_some()).count() - order.len();
+
+        let mut reached: Vec<(K,P,V)> = Vec::with_capacity(order.len());
+        for id in order {
+            if let Ok(node) = index.remove(&Some(id)) {
+                reached.push((node.key,node.priority,node.value));
+            }
+        }
+
+        // keep the longest run of rising keys, not just the ones above every key before
+        // them, so one misplaced key costs one entry rather than all that follow
+        let mut tails: Vec<usize> = Vec::new(); // last of the best run of each length
+        let mut before: Vec<Option<usize>> = Vec::with_capacity(reached.len());
+        for (i,(key,_,_)) in reached.iter().enumerate() {
+            let len = tails.partition_point(|&t| reached[t].0 < *key);
+            before.push(len.checked_sub(1).map(|l| tails[l]));
+            if len == tails.len() { tails.push(i); } else { tails[len] = i; }
+        }
+        let mut keep = vec![false; reached.len()];
+        let mut last = tails.last().copied();
+        while let Some(i) = last {
+            keep[i] = true;
+            last = before[i];
+        }
+        let entries: Vec<(K,P,V)> = reached.into_iter().zip(keep).filter_map(|(entry,keep)| keep.then_some(entry)).collect();
+        report.out_of_order = before.len() - entries.len();
+        report.salvaged = entries.len();
+        *self = Treap::build_sorted(entries);
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    type T = Treap<u32,u32,u32,Sum<u32>>;
+
+    fn treap() -> T {
+        (0 .. 200u32).map(|i| (i,i.wrapping_mul(2654435761) >> 20,i)).collect()
+    }
+
+    // entries, checking key order, heap order and aggregates on the way
+    fn check(t: &T, node: NodePtr, low: Option<u32>, high: Option<u32>) -> (usize,u32) {
+        let entry = match node {
+            Some(_) => t.index.get(&node).unwrap(),
+            None => return (0,0),
+        };
+        assert!(low.iter().all(|&low| low < entry.key) && high.iter().all(|&high| entry.key < high));
+        for child in [entry.left,entry.right].iter().filter(|c| c.is_some()) {
+            assert!(t.index.get(child).unwrap().priority <= entry.priority);
+        }
+        let (l,ls) = check(t,entry.left,low,Some(entry.key));
+        let (r,rs) = check(t,entry.right,Some(entry.key),high);
+        assert_eq!(entry.agg.0,ls + entry.value + rs);
+        (l + 1 + r,ls + entry.value + rs)
+    }
+    fn repaired(t: &mut T) -> RepairReport {
+        let report = t.repair();
+        assert_eq!(check(t,t.root,None,None).0,report.salvaged);
+        assert_eq!(t.len(),report.salvaged);
+        report
+    }
+    // a node with no children and the one above it
+    fn leaf(t: &T) -> (Id,Id) {
+        let (mut parent,mut id) = (t.root.unwrap(),t.root.unwrap());
+        while let Some(next) = { let e = t.index.get(&Some(id)).unwrap(); e.left.or(e.right) } {
+            parent = id;
+            id = next;
+        }
+        (parent,id)
+    }
+
+    #[test]
+    fn intact() {
+        let mut t = treap();
+        assert_eq!(repaired(&mut t),RepairReport { salvaged: 200, ..RepairReport::default() });
+    }
+
+    #[test]
+    fn dangling_link() {
+        let mut t = treap();
+        let (_,id) = leaf(&t);
+        t.index.index[id].as_mut().unwrap().left = Some(10_000);
+        assert_eq!(repaired(&mut t),RepairReport { salvaged: 200, dangling: 1, ..RepairReport::default() });
+    }
+
+    #[test]
+    fn cycle() {
+        let mut t = treap();
+        let (_,id) = leaf(&t);
+        t.index.index[id].as_mut().unwrap().right = t.root;
+        assert_eq!(repaired(&mut t),RepairReport { salvaged: 200, dangling: 1, ..RepairReport::default() });
+    }
+
+    #[test]
+    fn out_of_order_key() {
+        let mut t = treap();
+        let (parent,id) = leaf(&t);
+        let key = t.index.index[parent].as_ref().unwrap().key;
+        t.index.index[id].as_mut().unwrap().key = key + 1000;
+        assert_eq!(repaired(&mut t),RepairReport { salvaged: 199, out_of_order: 1, ..RepairReport::default() });
+        assert!(t.get(&(key + 1000)).unwrap().is_none());
+    }
+
+    #[test]
+    fn unreachable_slot() {
+        let mut t = treap();
+        let root = t.root.unwrap();
+        let entry = t.index.index[root].as_mut().unwrap();
+        entry.left = None;
+        let cut = entry.key as usize; // the keys below it
+        assert_eq!(repaired(&mut t),RepairReport { salvaged: 200 - cut, unreachable: cut, ..RepairReport::default() });
+    }
+}
@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+use core::hash::Hash;
+use alloc::vec::Vec;
+
+use crate::{Treap,Iter,Error};
+
+/// `Treap` with a hash index from values back to the keys holding them, for looking an
+/// entry up by value in O(1) expected time. Keys of one value are kept in insertion order.
+#[derive(Debug)]
+pub struct ReverseIndexedTreap<K,P,V> {
+    treap: Treap<K,P,V>,
+    keys: HashMap<V,Vec<K>>,
+}
+impl<K: PartialOrd + Clone,P: PartialOrd,V: Hash + Eq + Clone> Default for ReverseIndexedTreap<K,P,V> {
+    fn default() -> ReverseIndexedTreap<K,P,V> {
+        ReverseIndexedTreap::new()
+    }
+}
+impl<K: PartialOrd + Clone,P: PartialOrd,V: Hash + Eq + Clone> ReverseIndexedTreap<K,P,V> {
+    pub fn new() -> ReverseIndexedTreap<K,P,V> {
+        ReverseIndexedTreap { treap: Treap::new(), keys: HashMap::new() }
+    }
+    pub fn len(&self) -> usize {
+        self.treap.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.treap.is_empty()
+    }
+    /// The underlying treap, for read-only queries.
+    pub fn treap(&self) -> &Treap<K,P,V> {
+        &self.treap
+    }
+    pub fn insert(&mut self, key: K, priority: P, value: V) -> Result<Option<(P,V)>,Error> {
+        let replaced = self.treap.insert(key.clone(),priority,value.clone())?;
+        if let Some((_,old)) = &replaced { self.unlink(old,&key); }
+        self.keys.entry(value).or_default().push(key);
+        Ok(replaced)
+    }
+    pub fn remove(&mut self, key: &K) -> Result<Option<(P,V)>,Error> {
+        let removed = self.treap.remove(key)?;
+        if let Some((_,value)) = &removed { self.unlink(value,key); }
+        Ok(removed)
+    }
+    pub fn pop(&mut self) -> Result<Option<(K,P,V)>,Error> {
+        let popped = self.treap.pop()?;
+        if let Some((key,_,value)) = &popped { self.unlink(value,key); }
+        Ok(popped)
+    }
+    pub fn peek(&self) -> Result<Option<(&K,&P,&V)>,Error> {
+        self.treap.peek()
+    }
+    pub fn get(&self, key: &K) -> Result<Option<(&P,&V)>,Error> {
+        self.treap.get(key)
+    }
+    /// Earliest inserted key still holding `value`.
+    pub fn find_key_by_value(&self, value: &V) -> Option<&K> {
+        self.keys_by_value(value).first()
+    }
+    pub fn keys_by_value(&self, value: &V) -> &[K] {
+        match self.keys.get(value) {
+            Some(keys) => keys,
+            None => &[],
+        }
+    }
+    /// Removes every entry holding `value`, returning their keys and priorities.
+    pub fn remove_by_value(&mut self, value: &V) -> Result<Vec<(K,P)>,Error> {
+        let keys = self.keys.remove(value).unwrap_or_default();
+        let mut removed = Vec::with_capacity(keys.len());
+        for key in keys {
+            if let Some((priority,_)) = self.treap.remove(&key)? {
+                removed.push((key,priority));
+            }
+        }
+        Ok(removed)
+    }
+    pub fn iter(&self) -> Iter<'_,K,P,V> {
+        self.treap.iter()
+    }
+
+    fn unlink(&mut self, value: &V, key: &K) {
+        let emptied = match self.keys.get_mut(value) {
+            Some(keys) => {
+                if let Some(i) = keys.iter().position(|k| k == key) { keys.remove(i); }
+                keys.is_empty()
+            },
+            None => false,
+        };
+        if emptied { self.keys.remove(value); }
+    }
+}
+
+impl<'t,K: PartialOrd + Clone,P: PartialOrd,V: Hash + Eq + Clone> IntoIterator for &'t ReverseIndexedTreap<K,P,V> {
+    type Item = (&'t K, &'t P, &'t V);
+    type IntoIter = Iter<'t,K,P,V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
@@ -0,0 +1,286 @@
+use core::fmt;
+use core::ops::RangeBounds;
+use alloc::vec::Vec;
+use alloc::string::{String,ToString};
+
+use rand::{RngCore,SeedableRng};
+use rand::rngs::StdRng;
+
+use crate::{DirectVecIndex,Id,NodePtr,Error,IndexError};
+#[cfg(feature = "std")]
+use crate::priority::entropy_rng;
+use crate::implicit::bounds;
+
+const CHUNK: usize = 1024; // max bytes per chunk
+
+#[derive(Debug,Clone)]
+struct Chunk {
+    text: String,
+    chars: usize,
+    priority: u64,
+    size: usize,
+    total_chars: usize,
+    total_bytes: usize,
+    left: NodePtr,
+    right: NodePtr,
+}
+
+impl Chunk {
+    fn new(text: String, priority: u64) -> Chunk {
+        let chars = text.chars().count();
+        Chunk { chars, priority, size: 1, total_chars: chars, total_bytes: text.len(), text, left: None, right: None }
+    }
+    // recomputes the chunk count, chars and bytes of the subtree
+    fn update(index: &mut Index, node: NodePtr) -> Result<(),IndexError> {
+        let (l,r) = {
+            let entry = index.get(&node)?;
+            (entry.left,entry.right)
+        };
+        let (mut size,mut chars,mut bytes) = (1,0,0);
+        for child in [l,r].iter() {
+            if child.is_none() { continue; }
+            let entry = index.get(child)?;
+            size += entry.size;
+            chars += entry.total_chars;
+            bytes += entry.total_bytes;
+        }
+        let entry = index.get_mut(&node)?;
+        entry.size = size;
+        entry.total_chars = chars + entry.chars;
+        entry.total_bytes = bytes + entry.text.len();
+        Ok(())
+    }
+}
+
+type Index = DirectVecIndex<Chunk>;
+
+/// Text stored as chunks of UTF-8 on an implicit treap. Positions are in chars.
+#[derive(Debug)]
+pub struct Rope<R = StdRng> {
+    root: NodePtr,
+    index: Index,
+    rng: R,
+}
+#[cfg(feature = "std")]
+impl Default for Rope {
+    fn default() -> Rope {
+        Rope::new()
+    }
+}
+#[cfg(feature = "std")]
+impl Rope {
+    pub fn new() -> Rope {
+        Rope::with_rng(entropy_rng())
+    }
+}
+impl<R: SeedableRng + RngCore> Rope<R> {
+    pub fn with_seed(seed: u64) -> Rope<R> {
+        Rope::with_rng(R::seed_from_u64(seed))
+    }
+}
+impl<R: RngCore> Rope<R> {
+    pub fn with_rng(rng: R) -> Rope<R> {
+        Rope { root: None, index: Index::new(), rng }
+    }
+    pub fn len_chars(&self) -> usize {
+        self.index.get(&self.root).map(|entry| entry.total_chars).unwrap_or(0)
+    }
+    pub fn len_bytes(&self) -> usize {
+        self.index.get(&self.root).map(|entry| entry.total_bytes).unwrap_or(0)
+    }
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+    pub fn char_at(&self, i: usize) -> Result<Option<char>,Error> {
+        if i >= self.len_chars() { return Ok(None); }
+        let (n,_,offset) = find_char(&self.index,self.root,i).map_err(Error::Index)?;
+        Ok(self.index.get(&n).map_err(Error::Index)?.text.chars().nth(offset))
+    }
+    pub fn insert_str(&mut self, at: usize, s: &str) -> Result<(),Error> {
+        if at > self.len_chars() { return Err(Error::OutOfRange(at)); }
+        let (l,r) = self.split_chars(self.root,at).map_err(Error::Index)?;
+        let mut mid = None;
+        let mut rest = s;
+        while !rest.is_empty() {
+            let mut end = rest.len().min(CHUNK);
+            while !rest.is_char_boundary(end) { end -= 1; }
+            let (piece,tail) = rest.split_at(end);
+            let new = self.index.insert(Chunk::new(piece.to_string(),self.rng.next_u64()));
+            mid = merge_nodes(&mut self.index,mid,new).map_err(Error::Index)?;
+            rest = tail;
+        }
+        let l = self.weld(l,mid).map_err(Error::Index)?;
+        self.root = self.weld(l,r).map_err(Error::Index)?;
+        Ok(())
+    }
+    pub fn remove<B: RangeBounds<usize>>(&mut self, range: B) -> Result<(),Error> {
+        let (start,end) = bounds(range,self.len_chars())?;
+        let (l,r) = self.split_chars(self.root,start).map_err(Error::Index)?;
+        let (mid,r) = self.split_chars(r,end - start).map_err(Error::Index)?;
+        let mut stack: Vec<Id> = mid.into_iter().collect();
+        while let Some(id) = stack.pop() {
+            let entry = self.index.remove(&Some(id)).map_err(Error::Index)?;
+            stack.extend(entry.left);
+            stack.extend(entry.right);
+        }
+        self.root = self.weld(l,r).map_err(Error::Index)?;
+        Ok(())
+    }
+    pub fn chunks(&self) -> Chunks<'_> {
+        let mut chunks = Chunks { index: &self.index, stack: Vec::new() };
+        chunks.push_left(self.root);
+        chunks
+    }
+
+    // splits inside a chunk if `at` falls in the middle of one
+    fn split_chars(&mut self, node: NodePtr, at: usize) -> Result<(NodePtr,NodePtr),IndexError> {
+        let (_,i,offset) = find_char(&self.index,node,at)?;
+        let (l,r) = split_nodes(&mut self.index,node,i)?;
+        if offset == 0 { return Ok((l,r)); }
+        let (mid,r) = split_nodes(&mut self.index,r,1)?;
+        let tail = {
+            let entry = self.index.get_mut(&mid)?;
+            let byte = entry.text.char_indices().nth(offset).map(|(b,_)| b).unwrap_or(entry.text.len());
+            let tail = entry.text.split_off(byte);
+            entry.chars = offset;
+            tail
+        };
+        Chunk::update(&mut self.index,mid)?;
+        let new = self.index.insert(Chunk::new(tail,self.rng.next_u64()));
+        let l = merge_nodes(&mut self.index,l,mid)?;
+        let r = merge_nodes(&mut self.index,new,r)?;
+        Ok((l,r))
+    }
+    // merges, gluing the two boundary chunks together when they fit in one
+    fn weld(&mut self, left: NodePtr, right: NodePtr) -> Result<NodePtr,IndexError> {
+        if left.is_none() || right.is_none() { return merge_nodes(&mut self.index,left,right); }
+        let n = size(&self.index,left)?;
+        let (l,last) = split_nodes(&mut self.index,left,n - 1)?;
+        let (first,r) = split_nodes(&mut self.index,right,1)?;
+        let fits = self.index.get(&last)?.text.len() + self.index.get(&first)?.text.len() <= CHUNK;
+        let mid = if fits {
+            let first = self.index.remove(&first)?;
+            let entry = self.index.get_mut(&last)?;
+            entry.text.push_str(&first.text);
+            entry.chars += first.chars;
+            Chunk::update(&mut self.index,last)?;
+            last
+        } else {
+            merge_nodes(&mut self.index,last,first)?
+        };
+        let l = merge_nodes(&mut self.index,l,mid)?;
+        merge_nodes(&mut self.index,l,r)
+    }
+}
+
+impl<R> fmt::Display for Rope<R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut chunks = Chunks { index: &self.index, stack: Vec::new() };
+        chunks.push_left(self.root);
+        for chunk in chunks {
+            f.write_str(chunk)?;
+        }
+        Ok(())
+    }
+}
+
+pub struct Chunks<'t> {
+    index: &'t Index,
+    stack: Vec<Id>,
+}
+impl<'t> Chunks<'t> {
+    fn push_left(&mut self, mut node: NodePtr) {
+        while let Some(id) = node {
+            self.stack.push(id);
+            node = match self.index.get(&node) {
+                Ok(entry) => entry.left,
+                Err(_) => None,
+            };
+        }
+    }
+}
+impl<'t> Iterator for Chunks<'t> {
+    type Item = &'t str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let id = self.stack.pop()?;
+        match self.index.get(&Some(id)) {
+            Ok(entry) => {
+                self.push_left(entry.right);
+                Some(&entry.text)
+            },
+            Err(_) => {
+                self.stack.clear();
+                None
+            },
+        }
+    }
+}
+
+fn chars(index: &Index, node: NodePtr) -> Result<usize,IndexError> {
+    if node.is_none() { return Ok(0); }
+    Ok(index.get(&node)?.total_chars)
+}
+// chunk holding char `at`, its position and the offset in it; `None` past the end
+fn find_char(index: &Index, node: NodePtr, at: usize) -> Result<(NodePtr,usize,usize),IndexError> {
+    let (mut node,mut at,mut base) = (node,at,0);
+    while node.is_some() {
+        let entry = index.get(&node)?;
+        let lc = chars(index,entry.left)?;
+        if at < lc {
+            node = entry.left;
+        } else if at < lc + entry.chars {
+            return Ok((node,base + size(index,entry.left)?,at - lc));
+        } else {
+            at -= lc + entry.chars;
+            base += size(index,entry.left)? + 1;
+            node = entry.right;
+        }
+    }
+    Ok((None,base,0))
+}
+
+fn size(index: &Index, node: NodePtr) -> Result<usize,IndexError> {
+    if node.is_none() { return Ok(0); }
+    Ok(index.get(&node)?.size)
+}
+fn split_nodes(index: &mut Index, node: NodePtr, n: usize) -> Result<(NodePtr,NodePtr),IndexError> { // first n chunks go left
+    if node.is_none() { return Ok((None,None)); }
+    let (l,r) = {
+        let entry = index.get(&node)?;
+        (entry.left,entry.right)
+    };
+    let ls = size(index,l)?;
+    if n <= ls {
+        let (a,b) = split_nodes(index,l,n)?;
+        index.get_mut(&node)?.left = b;
+        Chunk::update(index,node)?;
+        Ok((a,node))
+    } else {
+        let (a,b) = split_nodes(index,r,n - ls - 1)?;
+        index.get_mut(&node)?.right = a;
+        Chunk::update(index,node)?;
+        Ok((node,b))
+    }
+}
+fn merge_nodes(index: &mut Index, left: NodePtr, right: NodePtr) -> Result<NodePtr,IndexError> {
+    if left.is_none() { return Ok(right); }
+    if right.is_none() { return Ok(left); }
+    let (left_p,left_right) = {
+        let entry = index.get(&left)?;
+        (entry.priority,entry.right)
+    };
+    let (right_p,right_left) = {
+        let entry = index.get(&right)?;
+        (entry.priority,entry.left)
+    };
+    if left_p > right_p {
+        index.get_mut(&left)?.right = merge_nodes(index,left_right,right)?;
+        Chunk::update(index,left)?;
+        Ok(left)
+    } else {
+        index.get_mut(&right)?.left = merge_nodes(index,left,right_left)?;
+        Chunk::update(index,right)?;
+        Ok(right)
+    }
+}
@@ -0,0 +1,119 @@
+use crate::{Treap,TreapMap,Error};
+use crate::map;
+
+// sequence numbers are ours, not the caller's, so a fixed seed can't be played against
+const ARRIVALS_SEED: u64 = 0x5345_5155_454e;
+
+/// `Treap` that also remembers the order entries arrived in, as `indexmap` does: every new
+/// key gets the next sequence number, kept while its entry is replaced and dropped when
+/// it is removed.
+#[derive(Debug)]
+pub struct SequencedTreap<K,P,V> {
+    treap: Treap<K,P,(u64,V)>,
+    arrivals: TreapMap<u64,K>,
+    next: u64,
+}
+impl<K: PartialOrd + Clone,P: PartialOrd,V> Default for SequencedTreap<K,P,V> {
+    fn default() -> SequencedTreap<K,P,V> {
+        SequencedTreap::new()
+    }
+}
+impl<K: PartialOrd + Clone,P: PartialOrd,V> SequencedTreap<K,P,V> {
+    pub fn new() -> SequencedTreap<K,P,V> {
+        SequencedTreap { treap: Treap::new(), arrivals: TreapMap::with_seed(ARRIVALS_SEED), next: 0 }
+    }
+    pub fn len(&self) -> usize {
+        self.treap.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.treap.is_empty()
+    }
+    pub fn insert(&mut self, key: K, priority: P, value: V) -> Result<Option<(P,V)>,Error> {
+        let seq = match self.treap.get(&key)? {
+            Some((_,(seq,_))) => *seq,
+            None => {
+                let seq = self.next;
+                self.next += 1;
+                self.arrivals.insert(seq,key.clone())?;
+                seq
+            },
+        };
+        Ok(self.treap.insert(key,priority,(seq,value))?.map(|(p,(_,v))| (p,v)))
+    }
+    pub fn remove(&mut self, key: &K) -> Result<Option<(P,V)>,Error> {
+        match self.treap.remove(key)? {
+            Some((p,(seq,v))) => {
+                self.arrivals.remove(&seq)?;
+                Ok(Some((p,v)))
+            },
+            None => Ok(None),
+        }
+    }
+    pub fn pop(&mut self) -> Result<Option<(K,P,V)>,Error> {
+        match self.treap.pop()? {
+            Some((k,p,(seq,v))) => {
+                self.arrivals.remove(&seq)?;
+                Ok(Some((k,p,v)))
+            },
+            None => Ok(None),
+        }
+    }
+    pub fn peek(&self) -> Result<Option<(&K,&P,&V)>,Error> {
+        Ok(self.treap.peek()?.map(|(k,p,(_,v))| (k,p,v)))
+    }
+    pub fn get(&self, key: &K) -> Result<Option<(&P,&V)>,Error> {
+        Ok(self.treap.get(key)?.map(|(p,(_,v))| (p,v)))
+    }
+    /// Sequence number `key` got when it arrived; later arrivals have larger ones.
+    pub fn insertion_index(&self, key: &K) -> Result<Option<u64>,Error> {
+        Ok(self.treap.get(key)?.map(|(_,(seq,_))| *seq))
+    }
+    /// Entries in key order.
+    pub fn iter(&self) -> Iter<'_,K,P,V> {
+        Iter { iter: self.treap.iter() }
+    }
+    /// Entries in the order they arrived.
+    pub fn iter_insertion_order(&self) -> Arrivals<'_,K,P,V> {
+        Arrivals { arrivals: self.arrivals.iter(), treap: &self.treap }
+    }
+}
+
+impl<'t,K: PartialOrd + Clone,P: PartialOrd,V> IntoIterator for &'t SequencedTreap<K,P,V> {
+    type Item = (&'t K, &'t P, &'t V);
+    type IntoIter = Iter<'t,K,P,V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct Iter<'t,K,P,V> {
+    iter: crate::Iter<'t,K,P,(u64,V)>,
+}
+impl<'t,K,P,V> Iterator for Iter<'t,K,P,V> {
+    type Item = (&'t K, &'t P, &'t V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|(k,p,(_,v))| (k,p,v))
+    }
+    fn size_hint(&self) -> (usize,Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+pub struct Arrivals<'t,K,P,V> {
+    arrivals: map::Iter<'t,u64,K>,
+    treap: &'t Treap<K,P,(u64,V)>,
+}
+impl<'t,K: PartialOrd,P: PartialOrd,V> Iterator for Arrivals<'t,K,P,V> {
+    type Item = (&'t K, &'t P, &'t V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (_,key) = self.arrivals.next()?;
+        let (p,(_,v)) = self.treap.get(key).ok()??; // stops on a corrupted arena, like `Iter`
+        Some((key,p,v))
+    }
+    fn size_hint(&self) -> (usize,Option<usize>) {
+        self.arrivals.size_hint()
+    }
+}
@@ -0,0 +1,202 @@
+// Serde support in two modes. Plain `Serialize`/`Deserialize` carry the content only: a
+// map from key to `(priority, value)` in key order, which any format can hold and which
+// restores to the same tree by the shape rules. `layout` carries the arena as it is, free
+// slots and generations included, so a restored treap is identical node for node and goes
+// on allocating the same ids, for deterministic replay and debugging.
+
+use core::fmt;
+use core::cmp::Ordering;
+use core::marker::PhantomData;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use serde::{Serialize,Serializer,Deserialize,Deserializer};
+use serde::ser::SerializeMap;
+use serde::de::{MapAccess,Visitor};
+
+#[cfg(feature = "bincode")]
+use serde::de::DeserializeOwned;
+#[cfg(feature = "bincode")]
+use std::io::{Read,Write};
+
+use crate::{Treap,Node,DirectVecIndex,SortedBuilder,Aggregate,HeapPolicy};
+#[cfg(feature = "bincode")]
+use crate::Error;
+
+impl<K: PartialOrd + Serialize,P: PartialOrd + Serialize,V: Serialize,A: Aggregate<V>,H: HeapPolicy> Serialize for Treap<K,P,V,A,H> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok,S::Error> {
+        let mut map = serializer.serialize_map(Some(self.len()))?;
+        for (key,priority,value) in self.iter() {
+            map.serialize_entry(key,&(priority,value))?;
+        }
+        map.end()
+    }
+}
+
+impl<'de,K,P,V,A,H> Deserialize<'de> for Treap<K,P,V,A,H>
+where K: PartialOrd + Deserialize<'de>, P: PartialOrd + Deserialize<'de>, V: Deserialize<'de>, A: Aggregate<V>, H: HeapPolicy {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Treap<K,P,V,A,H>,D::Error> {
+        struct Entries<K,P,V,A,H>(PhantomData<Treap<K,P,V,A,H>>);
+        impl<'de,K,P,V,A,H> Visitor<'de> for Entries<K,P,V,A,H>
+        where K: PartialOrd + Deserialize<'de>, P: PartialOrd + Deserialize<'de>, V: Deserialize<'de>, A: Aggregate<V>, H: HeapPolicy {
+            type Value = Treap<K,P,V,A,H>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a map from key to (priority, value)")
+            }
+            // built as the entries arrive, so nothing but the tree is held; only a map not
+            // in key order, which another producer may write, is gathered and sorted first
+            fn visit_map<M: MapAccess<'de>>(self, mut map: M) -> Result<Self::Value,M::Error> {
+                let mut builder: SortedBuilder<K,P,V,A,H> = SortedBuilder::with_capacity(map.size_hint().unwrap_or(0).min(4096));
+                while let Some((key,(priority,value))) = map.next_entry()? {
+                    if builder.last_key().is_some_and(|last| last.partial_cmp(&key) != Some(Ordering::Less)) {
+                        let mut entries = builder.into_entries();
+                        entries.push((key,priority,value));
+                        while let Some((key,(priority,value))) = map.next_entry()? {
+                            entries.push((key,priority,value));
+                        }
+                        return Treap::try_from_iter(entries).map_err(|_| serde::de::Error::custom("duplicate key"));
+                    }
+                    builder.push(key,priority,value);
+                }
+                Ok(builder.finish())
+            }
+        }
+
+        deserializer.deserialize_map(Entries(PhantomData))
+    }
+}
+
+impl<K,P,V,A,H> SortedBuilder<K,P,V,A,H> {
+    fn last_key(&self) -> Option<&K> {
+        self.nodes.last().map(|node| &node.key)
+    }
+    fn into_entries(self) -> Vec<(K,P,V)> { // in key order
+        self.nodes.into_iter().map(|node| (node.key,node.priority,node.value)).collect()
+    }
+}
+
+#[cfg(feature = "bincode")]
+impl<K,P,V,A,H> Treap<K,P,V,A,H>
+where K: PartialOrd + Serialize + DeserializeOwned, P: PartialOrd + Serialize + DeserializeOwned, V: Serialize + DeserializeOwned, A: Aggregate<V>, H: HeapPolicy {
+    /// Writes the entries to `writer` as bincode, one at a time straight from the tree, in
+    /// the content-only form. Small writes are passed through as they come, so a
+    /// `BufWriter` (or a compressor's own buffer) belongs in between.
+    pub fn serialize_into<W: Write>(&self, writer: W) -> Result<(),Error> {
+        bincode::serialize_into(writer,self).map_err(Error::Stream)
+    }
+    /// Reads a treap written by `serialize_into`, building the tree as the entries arrive:
+    /// besides the tree itself only one entry is held at a time.
+    pub fn deserialize_from<R: Read>(reader: R) -> Result<Treap<K,P,V,A,H>,Error> {
+        bincode::deserialize_from(reader).map_err(Error::Stream)
+    }
+}
+
+/// Structure-preserving mode, for `#[serde(with = "treap::layout")]` or for calling
+/// directly with a format's serializer. It records the node of every arena slot with its
+/// links, the free list and the slot generations; restoring checks all of it, so a
+/// tampered snapshot is an error, not a corrupted treap. Only the aggregates are
+/// recomputed, and the restored treap is a new arena: `SyncHandle`s don't carry over.
+pub mod layout {
+    use super::*;
+    use serde::ser::SerializeStruct;
+    use serde::de::Error as _;
+    use crate::{NodePtr,ARENAS,bump};
+
+    type Slot<K,P,V> = Option<(K,P,V,NodePtr,NodePtr)>;
+    type Visit = (usize,NodePtr,NodePtr,NodePtr); // node, parent, nodes bounding its key
+
+    struct Slots<'t,K,P,V,A>(&'t DirectVecIndex<Node<K,P,V,A>>);
+    impl<'t,K: Serialize,P: Serialize,V: Serialize,A> Serialize for Slots<'t,K,P,V,A> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok,S::Error> {
+            serializer.collect_seq(self.0.into_iter().map(|(_,slot)| {
+                slot.as_ref().map(|node| (&node.key,&node.priority,&node.value,node.left,node.right))
+            }))
+        }
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename = "Treap")]
+    struct Layout<K,P,V> {
+        root: NodePtr,
+        nodes: Vec<Slot<K,P,V>>,
+        free: Vec<usize>,
+        generations: Vec<u32>,
+    }
+
+    pub fn serialize<K,P,V,A,H,S>(treap: &Treap<K,P,V,A,H>, serializer: S) -> Result<S::Ok,S::Error>
+    where K: Serialize, P: Serialize, V: Serialize, S: Serializer {
+        let mut layout = serializer.serialize_struct("Treap",4)?;
+        layout.serialize_field("root",&treap.root)?;
+        layout.serialize_field("nodes",&Slots(&treap.index))?;
+        layout.serialize_field("free",&treap.index.reuse)?;
+        layout.serialize_field("generations",&treap.index.generations)?;
+        layout.end()
+    }
+
+    pub fn deserialize<'de,K,P,V,A,H,D>(deserializer: D) -> Result<Treap<K,P,V,A,H>,D::Error>
+    where K: PartialOrd + Deserialize<'de>, P: PartialOrd + Deserialize<'de>, V: Deserialize<'de>, A: Aggregate<V>, H: HeapPolicy, D: Deserializer<'de> {
+        let layout: Layout<K,P,V> = Layout::deserialize(deserializer)?;
+        let len = layout.nodes.len();
+        if layout.generations.len() != len {
+            return Err(D::Error::custom("generations don't match the slots"));
+        }
+
+        // every free slot listed once, and nothing else
+        let mut listed = vec![false; len];
+        for &id in &layout.free {
+            match layout.nodes.get(id) {
+                Some(None) if !listed[id] => listed[id] = true,
+                _ => return Err(D::Error::custom("free list names a used slot")),
+            }
+        }
+        if layout.free.len() != layout.nodes.iter().filter(|slot| slot.is_none()).count() {
+            return Err(D::Error::custom("free list misses a slot"));
+        }
+
+        // every used slot reached once from the root, in search and heap order; walked
+        // with a stack since the depth is the input's to choose
+        let mut reached = vec![false; len];
+        let mut order = Vec::with_capacity(len);
+        let mut stack: Vec<Visit> = layout.root.map(|id| (id,None,None,None)).into_iter().collect();
+        while let Some((id,parent,low,high)) = stack.pop() {
+            let (key,priority,_,left,right) = match layout.nodes.get(id) {
+                Some(Some(node)) if !reached[id] => node,
+                _ => return Err(D::Error::custom("link to a free or shared slot")),
+            };
+            reached[id] = true;
+            order.push(id);
+            let key_of = |id: usize| layout.nodes[id].as_ref().map(|node| &node.0);
+            if low.and_then(key_of).is_some_and(|low| low.partial_cmp(key) != Some(Ordering::Less))
+                || high.and_then(key_of).is_some_and(|high| key.partial_cmp(high) != Some(Ordering::Less)) {
+                return Err(D::Error::custom("keys out of order"));
+            }
+            if let Some(Some(above)) = parent.map(|parent| &layout.nodes[parent]) {
+                if H::rank((&above.0,&above.1),(key,priority)) != Some(Ordering::Greater) {
+                    return Err(D::Error::custom("priorities out of heap order"));
+                }
+            }
+            if let Some(left) = *left { stack.push((left,Some(id),low,Some(id))); }
+            if let Some(right) = *right { stack.push((right,Some(id),Some(id),high)); }
+        }
+        if order.len() + layout.free.len() != len {
+            return Err(D::Error::custom("slot unreachable from the root"));
+        }
+
+        let mut index = DirectVecIndex {
+            reuse: layout.free,
+            index: layout.nodes.into_iter().map(|slot| slot.map(|(key,priority,value,left,right)| {
+                let mut node = Node::new(key,priority,value);
+                node.left = left;
+                node.right = right;
+                node
+            })).collect(),
+            generations: layout.generations,
+            tag: bump(&ARENAS),
+        };
+        for &id in order.iter().rev() { // children before parents
+            Treap::update(&mut index,Some(id)).map_err(|_| D::Error::custom("broken link"))?;
+        }
+        Ok(Treap { root: layout.root, index, policy: PhantomData })
+    }
+}
@@ -0,0 +1,164 @@
+use core::ops::{RangeBounds,BitOr,BitAnd,Sub,BitXor};
+use core::hash::Hash;
+#[cfg(feature = "std")]
+use core::iter::FromIterator;
+
+use rand::{RngCore,SeedableRng};
+use rand::rngs::StdRng;
+
+use crate::{Treap,Error,PrioritySource,KeyHash};
+#[cfg(feature = "std")]
+use crate::priority::entropy_rng;
+
+/// Ordered set on top of `Treap` with priorities taken from a `PrioritySource` (an RNG by default).
+#[derive(Debug)]
+pub struct TreapSet<K,R = StdRng> {
+    treap: Treap<K,u64,()>,
+    source: R,
+}
+#[cfg(feature = "std")]
+impl<K: PartialOrd> Default for TreapSet<K> {
+    fn default() -> TreapSet<K> {
+        TreapSet::new()
+    }
+}
+#[cfg(feature = "std")]
+impl<K: PartialOrd> TreapSet<K> {
+    pub fn new() -> TreapSet<K> {
+        TreapSet::with_rng(entropy_rng())
+    }
+}
+impl<K: PartialOrd,R: SeedableRng + RngCore> TreapSet<K,R> {
+    pub fn with_seed(seed: u64) -> TreapSet<K,R> {
+        TreapSet::with_rng(R::seed_from_u64(seed))
+    }
+}
+impl<K: PartialOrd,R: RngCore> TreapSet<K,R> {
+    pub fn with_rng(rng: R) -> TreapSet<K,R> {
+        TreapSet::with_source(rng)
+    }
+}
+impl<K: PartialOrd + Hash> TreapSet<K,KeyHash> {
+    pub fn with_key_hash(seed: u64) -> TreapSet<K,KeyHash> {
+        TreapSet::with_source(KeyHash::new(seed))
+    }
+}
+impl<K: PartialOrd,R: PrioritySource<K>> TreapSet<K,R> {
+    pub fn with_source(source: R) -> TreapSet<K,R> {
+        TreapSet { treap: Treap::new(), source }
+    }
+    pub fn len(&self) -> usize {
+        self.treap.len()
+    }
+    /// Draws fresh priorities for every key and rebuilds in O(n), for when a run of unlucky
+    /// ones left the tree deep. With `KeyHash` the priorities, and so the shape, stay the same.
+    pub fn rebalance(&mut self) -> Result<(),Error> {
+        let source = &mut self.source;
+        self.treap.map_priorities(|key,_| source.priority(key))
+    }
+    pub fn is_empty(&self) -> bool {
+        self.treap.is_empty()
+    }
+    pub fn insert(&mut self, key: K) -> Result<bool,Error> {
+        let priority = self.source.priority(&key);
+        Ok(self.treap.insert(key,priority,())?.is_none())
+    }
+    pub fn remove(&mut self, key: &K) -> Result<bool,Error> {
+        Ok(self.treap.remove(key)?.is_some())
+    }
+    pub fn contains(&self, key: &K) -> Result<bool,Error> {
+        Ok(self.treap.priority(key)?.is_some())
+    }
+    /// Whether every key here is also in `other`, in O(n + m).
+    pub fn is_subset<S>(&self, other: &TreapSet<K,S>) -> bool {
+        self.treap.is_subset(&other.treap)
+    }
+    pub fn is_superset<S>(&self, other: &TreapSet<K,S>) -> bool {
+        other.treap.is_subset(&self.treap)
+    }
+    pub fn iter(&self) -> Iter<'_,K> {
+        Iter { iter: self.treap.iter() }
+    }
+    pub fn range<B: RangeBounds<K>>(&self, range: B) -> Range<'_,K> {
+        Range { iter: self.treap.range(range) }
+    }
+    pub fn union(self, other: TreapSet<K,R>) -> Result<TreapSet<K,R>,Error> {
+        Ok(TreapSet { treap: self.treap.union(other.treap).map_err(Error::Index)?, source: self.source })
+    }
+    pub fn intersection(self, other: TreapSet<K,R>) -> Result<TreapSet<K,R>,Error> {
+        Ok(TreapSet { treap: self.treap.intersection(other.treap).map_err(Error::Index)?, source: self.source })
+    }
+    pub fn difference(self, other: TreapSet<K,R>) -> Result<TreapSet<K,R>,Error> {
+        Ok(TreapSet { treap: self.treap.difference(other.treap).map_err(Error::Index)?, source: self.source })
+    }
+    pub fn symmetric_difference(self, other: TreapSet<K,R>) -> Result<TreapSet<K,R>,Error> {
+        Ok(TreapSet { treap: self.treap.symmetric_difference(other.treap)?, source: self.source })
+    }
+}
+
+impl<'t,K: PartialOrd,R: PrioritySource<K>> IntoIterator for &'t TreapSet<K,R> {
+    type Item = &'t K;
+    type IntoIter = Iter<'t,K>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+// Set algebra as operators, like std's sets but consuming both sides; the result is a
+// `Result` as the methods' are, so chains go `((a | b)? & c)?`.
+macro_rules! set_operator {
+    ($trait:ident, $op:ident, $method:ident) => {
+        impl<K: PartialOrd,R: PrioritySource<K>> $trait for TreapSet<K,R> {
+            type Output = Result<TreapSet<K,R>,Error>;
+
+            fn $op(self, other: TreapSet<K,R>) -> Self::Output {
+                self.$method(other)
+            }
+        }
+    };
+}
+set_operator!(BitOr,bitor,union);
+set_operator!(BitAnd,bitand,intersection);
+set_operator!(Sub,sub,difference);
+set_operator!(BitXor,bitxor,symmetric_difference);
+
+#[cfg(feature = "std")]
+impl<K: PartialOrd> FromIterator<K> for TreapSet<K> {
+    fn from_iter<I: IntoIterator<Item = K>>(iter: I) -> TreapSet<K> {
+        let mut source = entropy_rng();
+        let treap = iter.into_iter().map(|key| {
+            let priority = source.priority(&key);
+            (key,priority,())
+        }).collect();
+        TreapSet { treap, source }
+    }
+}
+
+pub struct Iter<'t,K> {
+    iter: crate::Iter<'t,K,u64,()>,
+}
+impl<'t,K> Iterator for Iter<'t,K> {
+    type Item = &'t K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|(k,_,_)| k)
+    }
+    fn size_hint(&self) -> (usize,Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+pub struct Range<'t,K> {
+    iter: crate::Range<'t,K,u64,()>,
+}
+impl<'t,K> Iterator for Range<'t,K> {
+    type Item = &'t K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|(k,_,_)| k)
+    }
+    fn size_hint(&self) -> (usize,Option<usize>) {
+        self.iter.size_hint()
+    }
+}
@@ -0,0 +1,120 @@
+use core::ops::RangeBounds;
+use core::hash::Hash;
+use core::iter::Peekable;
+use std::sync::{RwLock,RwLockReadGuard,RwLockWriteGuard};
+use alloc::vec::Vec;
+
+use crate::{TreapMap,Error,PrioritySource,KeyHash};
+use crate::map::{Iter,Range};
+
+const ROUTER_SEED: u64 = 0x5348_4152_4453; // fixed so a key always lands in the same shard
+
+/// Concurrent ordered map: keys are spread over `TreapMap` shards by hash, each behind its
+/// own lock, so writers to different shards don't contend. Whole-map reads go through
+/// `read`, which stitches the shards back into key order.
+#[derive(Debug)]
+pub struct ShardedTreapMap<K,V> {
+    shards: Vec<RwLock<TreapMap<K,V>>>,
+    router: KeyHash,
+}
+impl<K: PartialOrd + Hash,V> ShardedTreapMap<K,V> {
+    pub fn new(shards: usize) -> ShardedTreapMap<K,V> {
+        ShardedTreapMap {
+            shards: (0 .. shards.max(1)).map(|_| RwLock::new(TreapMap::new())).collect(),
+            router: KeyHash::new(ROUTER_SEED),
+        }
+    }
+    pub fn shards(&self) -> usize {
+        self.shards.len()
+    }
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|shard| read(shard).len()).sum()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.shards.iter().all(|shard| read(shard).is_empty())
+    }
+    pub fn insert(&self, key: K, value: V) -> Result<Option<V>,Error> {
+        write(self.shard(&key)).insert(key,value)
+    }
+    pub fn remove(&self, key: &K) -> Result<Option<V>,Error> {
+        write(self.shard(key)).remove(key)
+    }
+    pub fn contains_key(&self, key: &K) -> Result<bool,Error> {
+        read(self.shard(key)).contains_key(key)
+    }
+    /// Runs `f` on the value of `key` under its shard's read lock.
+    pub fn with<T, F: FnOnce(Option<&V>) -> T>(&self, key: &K, f: F) -> Result<T,Error> {
+        Ok(f(read(self.shard(key)).get(key)?))
+    }
+    /// Runs `f` on the value of `key` under its shard's write lock.
+    pub fn with_mut<T, F: FnOnce(Option<&mut V>) -> T>(&self, key: &K, f: F) -> Result<T,Error> {
+        Ok(f(write(self.shard(key)).get_mut(key)?))
+    }
+    /// Read-locks every shard, for a consistent view across all of them.
+    pub fn read(&self) -> ShardedRead<'_,K,V> {
+        ShardedRead { shards: self.shards.iter().map(read).collect() }
+    }
+    fn shard(&self, key: &K) -> &RwLock<TreapMap<K,V>> {
+        let mut router = self.router;
+        &self.shards[(router.priority(key) % self.shards.len() as u64) as usize]
+    }
+}
+impl<K: PartialOrd + Hash + Clone,V: Clone> ShardedTreapMap<K,V> {
+    pub fn get(&self, key: &K) -> Result<Option<V>,Error> {
+        self.with(key,|value| value.cloned())
+    }
+}
+
+// shard updates are single treap operations, so a panicking holder leaves no torn state
+fn read<K,V>(shard: &RwLock<TreapMap<K,V>>) -> RwLockReadGuard<'_,TreapMap<K,V>> {
+    shard.read().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+fn write<K,V>(shard: &RwLock<TreapMap<K,V>>) -> RwLockWriteGuard<'_,TreapMap<K,V>> {
+    shard.write().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// All shards of a `ShardedTreapMap` read-locked at once.
+pub struct ShardedRead<'t,K,V> {
+    shards: Vec<RwLockReadGuard<'t,TreapMap<K,V>>>,
+}
+impl<'t,K: PartialOrd,V> ShardedRead<'t,K,V> {
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|shard| shard.len()).sum()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.shards.iter().all(|shard| shard.is_empty())
+    }
+    pub fn iter(&self) -> Merge<Iter<'_,K,V>> {
+        Merge::new(self.shards.iter().map(|shard| shard.iter()))
+    }
+    pub fn range<B: RangeBounds<K> + Clone>(&self, range: B) -> Merge<Range<'_,K,V>> {
+        Merge::new(self.shards.iter().map(|shard| shard.range(range.clone())))
+    }
+}
+
+/// Per-shard iterators merged into key order.
+pub struct Merge<I: Iterator> {
+    iters: Vec<Peekable<I>>,
+}
+impl<I: Iterator> Merge<I> {
+    fn new<S: Iterator<Item = I>>(iters: S) -> Merge<I> {
+        Merge { iters: iters.map(Iterator::peekable).collect() }
+    }
+}
+impl<'t,K: PartialOrd + 't,V: 't,I: Iterator<Item = (&'t K, &'t V)>> Iterator for Merge<I> {
+    type Item = (&'t K, &'t V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut first: Option<(usize,&K)> = None;
+        for (i,iter) in self.iters.iter_mut().enumerate() {
+            if let Some((key,_)) = iter.peek() {
+                let smaller = match first {
+                    Some((_,min)) => *key < min,
+                    None => true,
+                };
+                if smaller { first = Some((i,key)); }
+            }
+        }
+        self.iters[first?.0].next()
+    }
+}
@@ -0,0 +1,61 @@
+use std::sync::{Arc,Mutex};
+
+use arc_swap::ArcSwap;
+
+use crate::{PersistentTreap,CowTreap};
+
+/// Persistent treap shared between threads in RCU style. Readers load the current version
+/// without locking and keep traversing it however long they like; writers build the next
+/// version by path copying and publish it with one atomic swap. Writers are serialized
+/// among themselves but never block readers.
+#[derive(Debug)]
+pub struct SharedTreap<K,P,V> {
+    current: ArcSwap<PersistentTreap<K,P,V>>,
+    writer: Mutex<()>,
+}
+impl<K,P,V> Default for SharedTreap<K,P,V> {
+    fn default() -> SharedTreap<K,P,V> {
+        SharedTreap::new()
+    }
+}
+impl<K,P,V> From<PersistentTreap<K,P,V>> for SharedTreap<K,P,V> {
+    fn from(treap: PersistentTreap<K,P,V>) -> SharedTreap<K,P,V> {
+        SharedTreap { current: ArcSwap::from_pointee(treap), writer: Mutex::new(()) }
+    }
+}
+impl<K,P,V> SharedTreap<K,P,V> {
+    pub fn new() -> SharedTreap<K,P,V> {
+        SharedTreap::from(PersistentTreap::new())
+    }
+    /// Latest published version; later writes don't affect it.
+    pub fn snapshot(&self) -> Arc<PersistentTreap<K,P,V>> {
+        self.current.load_full()
+    }
+    /// Runs `f` on the latest published version without taking a reference count.
+    pub fn read<T, F: FnOnce(&PersistentTreap<K,P,V>) -> T>(&self, f: F) -> T {
+        f(&self.current.load())
+    }
+    /// Publishes the version `f` builds from the latest one.
+    pub fn update<F: FnOnce(&PersistentTreap<K,P,V>) -> PersistentTreap<K,P,V>>(&self, f: F) {
+        let _writer = self.writer.lock().unwrap_or_else(|poisoned| poisoned.into_inner()); // guards no data
+        let next = f(&self.current.load());
+        self.current.store(Arc::new(next));
+    }
+    /// Runs `f` on a private copy of the latest version and publishes the result only if
+    /// `f` returns `Ok`; readers see either all of its updates or none.
+    pub fn transaction<T, E, F: FnOnce(&mut CowTreap<K,P,V>) -> Result<T,E>>(&self, f: F) -> Result<T,E> {
+        let _writer = self.writer.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut txn = CowTreap::from(PersistentTreap::clone(&self.current.load()));
+        let result = f(&mut txn)?;
+        self.current.store(Arc::new(txn.snapshot()));
+        Ok(result)
+    }
+}
+impl<K: PartialOrd + Clone,P: PartialOrd + Clone,V: Clone> SharedTreap<K,P,V> {
+    pub fn insert(&self, key: K, priority: P, value: V) {
+        self.update(|treap| treap.insert(key,priority,value));
+    }
+    pub fn remove(&self, key: &K) {
+        self.update(|treap| treap.remove(key));
+    }
+}
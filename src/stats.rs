@@ -0,0 +1,62 @@
+use core::sync::atomic::{AtomicUsize,Ordering};
+use core::cmp::Ordering as CmpOrdering;
+use alloc::vec::Vec;
+
+use crate::{Treap,Aggregate,HeapPolicy,Error,bump};
+
+// Logical clock shared by all treaps: cheaper than reading the system time on every
+// `get`, and all eviction needs is the order of accesses.
+static TICKS: AtomicUsize = AtomicUsize::new(0);
+
+/// Reads of one entry through `get` and `get_mut` since it was inserted.
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Default)]
+pub struct AccessStats {
+    pub count: usize,
+    /// Tick of the latest read, from a clock counting reads of all treaps; `None` if unread.
+    pub last_access: Option<usize>,
+}
+
+#[derive(Debug,Default)]
+pub(crate) struct Counters {
+    count: AtomicUsize,
+    last: AtomicUsize, // tick + 1, 0 for never
+}
+impl Counters {
+    pub(crate) fn touch(&self) {
+        bump(&self.count);
+        self.last.store(bump(&TICKS).wrapping_add(1),Ordering::Relaxed);
+    }
+    fn load(&self) -> AccessStats {
+        AccessStats {
+            count: self.count.load(Ordering::Relaxed),
+            last_access: self.last.load(Ordering::Relaxed).checked_sub(1),
+        }
+    }
+}
+impl Clone for Counters {
+    fn clone(&self) -> Counters {
+        Counters { count: AtomicUsize::new(self.count.load(Ordering::Relaxed)), last: AtomicUsize::new(self.last.load(Ordering::Relaxed)) }
+    }
+}
+
+impl<K: PartialOrd,P: PartialOrd,V,A: Aggregate<V>,H: HeapPolicy> Treap<K,P,V,A,H> {
+    pub fn access_stats(&self, key: &K) -> Result<Option<AccessStats>,Error> {
+        let mut node = self.root;
+        while node.is_some() {
+            let entry = self.index.get(&node).map_err(Error::Index)?;
+            if entry.key == *key { return Ok(Some(entry.stats.load())); }
+            node = if entry.key > *key { entry.left } else { entry.right };
+        }
+        Ok(None)
+    }
+    /// Entries coldest first: never read before read, then least recently read, then
+    /// least often.
+    pub fn coldest(&self) -> impl Iterator<Item = (&K,&P,&V,AccessStats)> {
+        let mut entries: Vec<_> = self.index.index.iter().flatten().map(|node| (&node.key,&node.priority,&node.value,node.stats.load())).collect();
+        entries.sort_by(|a,b| match a.3.last_access.cmp(&b.3.last_access) {
+            CmpOrdering::Equal => a.3.count.cmp(&b.3.count),
+            order => order,
+        });
+        entries.into_iter()
+    }
+}
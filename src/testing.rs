@@ -0,0 +1,89 @@
+// Generators of treaps for property tests downstream: `quickcheck::Arbitrary` and
+// `proptest` strategies. Besides random priorities they make the degenerate patterns
+// that stress the shape code.
+
+use alloc::vec::Vec;
+
+use crate::{Treap,Aggregate,HeapPolicy};
+
+/// How the priorities of a generated treap are laid out.
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum Priorities {
+    /// As generated.
+    Random,
+    /// All equal, leaving the shape to the tie break.
+    Equal,
+    /// Rising with the key, so the treap is a single path.
+    Rising,
+}
+
+fn arrange<K: PartialOrd,P: PartialOrd + Clone,V,A: Aggregate<V>,H: HeapPolicy>(mut entries: Vec<(K,P,V)>, pattern: Priorities) -> Treap<K,P,V,A,H> {
+    match pattern {
+        Priorities::Random => {},
+        Priorities::Equal => if let Some((_,p,_)) = entries.first() {
+            let p = p.clone();
+            for entry in &mut entries { entry.1 = p.clone(); }
+        },
+        Priorities::Rising => {
+            entries.sort_by(|a,b| a.0.partial_cmp(&b.0).unwrap_or(core::cmp::Ordering::Equal));
+            let mut priorities: Vec<P> = entries.iter().map(|(_,p,_)| p.clone()).collect();
+            priorities.sort_by(|a,b| H::compare(a,b).unwrap_or(core::cmp::Ordering::Equal));
+            for (entry,p) in entries.iter_mut().zip(priorities) { entry.1 = p; }
+        },
+    }
+    entries.into_iter().collect()
+}
+
+#[cfg(feature = "quickcheck")]
+mod quickcheck_impl {
+    use super::*;
+    use alloc::boxed::Box;
+    use quickcheck::{Arbitrary,Gen};
+
+    impl Arbitrary for Priorities {
+        fn arbitrary(g: &mut Gen) -> Priorities {
+            *g.choose(&[Priorities::Random,Priorities::Equal,Priorities::Rising]).unwrap_or(&Priorities::Random)
+        }
+    }
+
+    impl<K,P,V,A,H> Arbitrary for Treap<K,P,V,A,H>
+    where K: Arbitrary + PartialOrd, P: Arbitrary + PartialOrd, V: Arbitrary, A: Aggregate<V> + 'static, H: HeapPolicy + 'static {
+        fn arbitrary(g: &mut Gen) -> Treap<K,P,V,A,H> {
+            arrange(Vec::arbitrary(g),Priorities::arbitrary(g))
+        }
+        /// Shrinks the entries; the priority pattern may be lost on the way.
+        fn shrink(&self) -> Box<dyn Iterator<Item = Treap<K,P,V,A,H>>> {
+            let entries: Vec<(K,P,V)> = self.iter().map(|(k,p,v)| (k.clone(),p.clone(),v.clone())).collect();
+            Box::new(entries.shrink().map(|entries| entries.into_iter().collect()))
+        }
+    }
+}
+
+#[cfg(feature = "proptest")]
+mod proptest_impl {
+    use super::*;
+    use core::fmt::Debug;
+    use proptest::prelude::*;
+    use proptest::collection::{vec,SizeRange};
+
+    /// Treaps of `size` entries drawn from the given strategies, in every `Priorities`
+    /// pattern; duplicate keys are merged, so a treap may come out smaller.
+    pub fn treap<K,P,V,A,H>(key: impl Strategy<Value = K>, priority: impl Strategy<Value = P>, value: impl Strategy<Value = V>, size: impl Into<SizeRange>) -> impl Strategy<Value = Treap<K,P,V,A,H>>
+    where K: PartialOrd + Debug, P: PartialOrd + Clone + Debug, V: Debug, A: Aggregate<V> + Debug, H: HeapPolicy + Debug {
+        let pattern = prop_oneof![Just(Priorities::Random),Just(Priorities::Equal),Just(Priorities::Rising)];
+        (vec((key,priority,value),size),pattern).prop_map(|(entries,pattern)| arrange(entries,pattern))
+    }
+
+    impl<K,P,V,A,H> Arbitrary for Treap<K,P,V,A,H>
+    where K: Arbitrary + PartialOrd + 'static, P: Arbitrary + PartialOrd + Clone + 'static, V: Arbitrary + 'static, A: Aggregate<V> + Debug + 'static, H: HeapPolicy + Debug + 'static,
+        K::Strategy: 'static, P::Strategy: 'static, V::Strategy: 'static {
+        type Parameters = ();
+        type Strategy = BoxedStrategy<Treap<K,P,V,A,H>>;
+
+        fn arbitrary_with(_: ()) -> Self::Strategy {
+            treap(any::<K>(),any::<P>(),any::<V>(),0 .. 100).boxed()
+        }
+    }
+}
+#[cfg(feature = "proptest")]
+pub use proptest_impl::treap;
@@ -0,0 +1,148 @@
+use core::ops::{Bound,RangeBounds};
+use alloc::vec;
+
+use crate::{Treap,Node,Index,NodePtr,Iter,Range,Aggregate,HeapPolicy,MaxPolicy,Error,IndexError};
+use crate::iter::{after_start,before_end};
+
+/// Borrowed view of one node and everything beneath it, for analyses where the shape of
+/// the treap matters.
+pub struct Subtree<'t,K,P,V,A = ()> {
+    index: &'t Index<K,P,V,A>,
+    node: &'t Node<K,P,V,A>,
+    root: NodePtr,
+}
+
+impl<K: PartialOrd,P: PartialOrd,V,A: Aggregate<V>,H: HeapPolicy> Treap<K,P,V,A,H> {
+    /// Subtree rooted at the node holding `key`.
+    pub fn subtree(&self, key: &K) -> Result<Option<Subtree<'_,K,P,V,A>>,Error> {
+        let mut node = self.root;
+        while node.is_some() {
+            let entry = self.index.get(&node).map_err(Error::Index)?;
+            if entry.key == *key { return Ok(Some(Subtree { index: &self.index, node: entry, root: node })); }
+            node = if entry.key > *key { entry.left } else { entry.right };
+        }
+        Ok(None)
+    }
+    /// The whole tree as a subtree, `None` if empty.
+    pub fn root_subtree(&self) -> Result<Option<Subtree<'_,K,P,V,A>>,Error> {
+        Subtree::at(&self.index,self.root).map_err(Error::Index)
+    }
+}
+
+impl<'t,K,P,V,A> Subtree<'t,K,P,V,A> {
+    fn at(index: &'t Index<K,P,V,A>, root: NodePtr) -> Result<Option<Subtree<'t,K,P,V,A>>,IndexError> {
+        if root.is_none() { return Ok(None); }
+        Ok(Some(Subtree { index, node: index.get(&root)?, root }))
+    }
+    /// Entry at the top of the subtree.
+    pub fn root(&self) -> (&'t K,&'t P,&'t V) {
+        (&self.node.key,&self.node.priority,&self.node.value)
+    }
+    pub fn left(&self) -> Result<Option<Subtree<'t,K,P,V,A>>,Error> {
+        Subtree::at(self.index,self.node.left).map_err(Error::Index)
+    }
+    pub fn right(&self) -> Result<Option<Subtree<'t,K,P,V,A>>,Error> {
+        Subtree::at(self.index,self.node.right).map_err(Error::Index)
+    }
+    /// Aggregate of every value in the subtree, kept up to date in the node.
+    pub fn aggregate(&self) -> &'t A {
+        &self.node.agg
+    }
+    /// Number of entries, counted by walking the subtree; never 0.
+    pub fn size(&self) -> Result<usize,Error> {
+        let mut count = 0;
+        let mut stack = vec![self.root];
+        while let Some(node) = stack.pop() {
+            if node.is_none() { continue; }
+            let entry = self.index.get(&node).map_err(Error::Index)?;
+            count += 1;
+            stack.push(entry.left);
+            stack.push(entry.right);
+        }
+        Ok(count)
+    }
+    pub fn depth(&self) -> Result<usize,Error> {
+        fn depth_node<K,P,V,A>(index: &Index<K,P,V,A>, node: NodePtr) -> Result<usize,IndexError> {
+            if node.is_none() { return Ok(0); }
+            let entry = index.get(&node)?;
+            Ok(1 + usize::max(depth_node(index,entry.left)?,depth_node(index,entry.right)?))
+        }
+
+        depth_node(self.index,self.root).map_err(Error::Index)
+    }
+    /// Entries of the subtree in key order.
+    pub fn iter(&self) -> Result<Iter<'t,K,P,V,A>,Error> {
+        Ok(Iter::new(self.index,self.root,self.size()?))
+    }
+}
+
+/// Borrowed view of the entries with keys in one range, queried without copying them out.
+pub struct RangeView<'t,K,P,V,B,A = (),H = MaxPolicy> {
+    treap: &'t Treap<K,P,V,A,H>,
+    range: B,
+}
+
+impl<K: PartialOrd,P: PartialOrd,V,A: Aggregate<V>,H: HeapPolicy> Treap<K,P,V,A,H> {
+    pub fn view<B: RangeBounds<K>>(&self, range: B) -> RangeView<'_,K,P,V,B,A,H> {
+        RangeView { treap: self, range }
+    }
+}
+
+impl<'t,K: PartialOrd,P: PartialOrd,V,B: RangeBounds<K>,A: Aggregate<V>,H: HeapPolicy> RangeView<'t,K,P,V,B,A,H> {
+    fn bounds(&self) -> (Bound<&K>,Bound<&K>) {
+        (self.range.start_bound(),self.range.end_bound())
+    }
+    pub fn iter(&self) -> Range<'t,K,P,V,A> {
+        self.treap.range(self.bounds())
+    }
+    /// Number of entries, counted by walking them.
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+    pub fn contains_key(&self, key: &K) -> Result<bool,Error> {
+        Ok(self.range.contains(key) && self.treap.get(key)?.is_some())
+    }
+    /// Entry with the smallest key, in O(log n).
+    pub fn min(&self) -> Result<Option<(&'t K,&'t P,&'t V)>,Error> {
+        let mut found = None;
+        let mut node = self.treap.root;
+        while node.is_some() {
+            let entry = self.treap.index.get(&node).map_err(Error::Index)?;
+            node = if after_start(&entry.key,self.range.start_bound()) {
+                found = Some(entry);
+                entry.left
+            } else {
+                entry.right
+            };
+        }
+        Ok(found.filter(|entry| before_end(&entry.key,self.range.end_bound())).map(|entry| (&entry.key,&entry.priority,&entry.value)))
+    }
+    /// Entry with the largest key, in O(log n).
+    pub fn max(&self) -> Result<Option<(&'t K,&'t P,&'t V)>,Error> {
+        let mut found = None;
+        let mut node = self.treap.root;
+        while node.is_some() {
+            let entry = self.treap.index.get(&node).map_err(Error::Index)?;
+            node = if before_end(&entry.key,self.range.end_bound()) {
+                found = Some(entry);
+                entry.right
+            } else {
+                entry.left
+            };
+        }
+        Ok(found.filter(|entry| after_start(&entry.key,self.range.start_bound())).map(|entry| (&entry.key,&entry.priority,&entry.value)))
+    }
+    /// Top-ranked entry, in O(log n).
+    pub fn top(&self) -> Result<Option<(&'t K,&'t P,&'t V)>,Error> {
+        self.treap.max_priority_entry_in_range(self.bounds())
+    }
+}
+impl<'t,K: PartialOrd,P: PartialOrd,V,B: RangeBounds<K>,A: Aggregate<V> + Clone,H: HeapPolicy> RangeView<'t,K,P,V,B,A,H> {
+    /// Aggregate of the values, in O(log n).
+    pub fn aggregate(&self) -> Result<Option<A>,Error> {
+        self.treap.aggregate_range(self.bounds())
+    }
+}
@@ -0,0 +1,39 @@
+use alloc::vec::Vec;
+
+use crate::{Treap,Iter,Error};
+
+/// Entries keyed by a moving position (time, sequence number); the highest priority still
+/// inside the window sits at the root.
+#[derive(Debug)]
+pub struct SlidingWindow<K,P,V> {
+    treap: Treap<K,P,V>,
+}
+impl<K: PartialOrd,P: PartialOrd,V> Default for SlidingWindow<K,P,V> {
+    fn default() -> SlidingWindow<K,P,V> {
+        SlidingWindow::new()
+    }
+}
+impl<K: PartialOrd,P: PartialOrd,V> SlidingWindow<K,P,V> {
+    pub fn new() -> SlidingWindow<K,P,V> {
+        SlidingWindow { treap: Treap::new() }
+    }
+    pub fn len(&self) -> usize {
+        self.treap.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.treap.is_empty()
+    }
+    pub fn push(&mut self, key: K, priority: P, value: V) -> Result<Option<(P,V)>,Error> {
+        self.treap.insert(key,priority,value)
+    }
+    /// Drops every entry keyed strictly below `key`, returning them in key order.
+    pub fn evict_before(&mut self, key: &K) -> Result<Vec<(K,P,V)>,Error> {
+        self.treap.take_below(key).map_err(Error::Index)
+    }
+    pub fn current_max(&self) -> Result<Option<(&K,&P,&V)>,Error> {
+        self.treap.peek()
+    }
+    pub fn iter(&self) -> Iter<'_,K,P,V> {
+        self.treap.iter()
+    }
+}
@@ -0,0 +1,46 @@
+// Random operation sequences replayed against a treap and the reference model in
+// `model/`; the fuzz target in `fuzz/` drives the same model from libFuzzer input.
+
+mod model;
+
+use treap::{MaxPolicy,MinPolicy,ReverseTies};
+
+// xorshift64*, so a failure reproduces from its seed alone
+fn bytes(seed: u64, len: usize) -> Vec<u8> {
+    let mut state = seed | 1;
+    (0 .. len).map(|_| {
+        state ^= state >> 12;
+        state ^= state << 25;
+        state ^= state >> 27;
+        (state.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 56) as u8
+    }).collect()
+}
+
+#[test]
+fn max_policy() {
+    for seed in 0 .. 200 {
+        model::replay::<MaxPolicy>(&model::decode(&bytes(seed,1500)));
+    }
+}
+
+#[test]
+fn min_policy() {
+    for seed in 0 .. 200 {
+        model::replay::<MinPolicy>(&model::decode(&bytes(seed,1500)));
+    }
+}
+
+#[test]
+fn reverse_ties() {
+    for seed in 0 .. 200 {
+        model::replay::<ReverseTies<MaxPolicy>>(&model::decode(&bytes(seed,1500)));
+    }
+}
+
+#[test]
+fn short_sequences() {
+    // short runs stay near empty, where the edge cases are
+    for seed in 0 .. 5000 {
+        model::replay::<MaxPolicy>(&model::decode(&bytes(seed,24)));
+    }
+}
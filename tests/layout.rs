@@ -0,0 +1,142 @@
+// `layout` snapshots taken apart, tampered with and fed back: each has to be refused
+// rather than come back as a treap with a broken arena.
+#![cfg(feature = "bincode")]
+
+use serde::{Serialize,Deserialize};
+use treap::Treap;
+
+type T = Treap<u32,u32,u32>;
+type Slot = (u32,u32,u32,Option<usize>,Option<usize>); // key, priority, value, links
+
+#[derive(Serialize,Deserialize)]
+struct Exact(#[serde(with = "treap::layout")] T);
+
+// the same fields `layout` writes, open to editing
+#[derive(Clone,Serialize,Deserialize)]
+struct Snapshot {
+    root: Option<usize>,
+    nodes: Vec<Option<Slot>>,
+    free: Vec<usize>,
+    generations: Vec<u32>,
+}
+
+impl Snapshot {
+    fn node(&mut self, id: usize) -> &mut Slot {
+        self.nodes[id].as_mut().unwrap()
+    }
+    // the slots down one side from the root
+    fn spine(&self, right: bool) -> Vec<usize> {
+        let mut ids = vec![self.root.unwrap()];
+        while let Some(&(_,_,_,l,r)) = self.nodes[*ids.last().unwrap()].as_ref() {
+            match if right { r } else { l } {
+                Some(id) => ids.push(id),
+                None => break,
+            }
+        }
+        ids
+    }
+}
+
+fn snapshot() -> Snapshot {
+    let mut t: T = (0 .. 100u32).map(|i| (i,i.wrapping_mul(2654435761) >> 8,i)).collect();
+    for key in (0 .. 100).step_by(7) { t.remove(&key).unwrap(); }
+    bincode::deserialize(&bincode::serialize(&Exact(t)).unwrap()).unwrap()
+}
+
+fn refused(snapshot: &Snapshot, reason: &str) {
+    let bytes = bincode::serialize(snapshot).unwrap();
+    match bincode::deserialize::<Exact>(&bytes) {
+        Ok(_) => panic!("accepted a snapshot with {}",reason),
+        Err(e) => assert!(e.to_string().contains(reason),"{}",e),
+    }
+}
+
+#[test]
+fn untouched() {
+    let s = snapshot();
+    let Exact(t) = bincode::deserialize(&bincode::serialize(&s).unwrap()).unwrap();
+    assert!(t.iter().map(|(k,_,_)| *k).eq((0 .. 100).filter(|k| k % 7 != 0)));
+}
+
+#[test]
+fn cycle() {
+    let mut s = snapshot();
+    let leaf = *s.spine(false).last().unwrap();
+    s.node(leaf).3 = s.root;
+    refused(&s,"shared slot");
+}
+
+#[test]
+fn shared_child() {
+    let mut s = snapshot();
+    let (left,right) = (*s.spine(false).last().unwrap(),*s.spine(true).last().unwrap());
+    s.node(left).3 = Some(right);
+    refused(&s,"shared slot");
+}
+
+#[test]
+fn heap_order() {
+    let mut s = snapshot();
+    let leaf = *s.spine(true).last().unwrap();
+    s.node(leaf).1 = u32::MAX;
+    refused(&s,"heap order");
+}
+
+#[test]
+fn key_order() {
+    let mut s = snapshot();
+    let leaf = *s.spine(false).last().unwrap();
+    s.node(leaf).0 = 1000;
+    refused(&s,"keys out of order");
+}
+
+#[test]
+fn out_of_range_id() {
+    let mut s = snapshot();
+    let leaf = *s.spine(true).last().unwrap();
+    s.node(leaf).4 = Some(s.nodes.len());
+    refused(&s,"free or shared slot");
+
+    let mut s = snapshot();
+    s.root = Some(usize::MAX);
+    refused(&s,"free or shared slot");
+
+    let mut s = snapshot();
+    s.free.push(s.nodes.len());
+    refused(&s,"free list names a used slot");
+}
+
+#[test]
+fn link_to_free_slot() {
+    let mut s = snapshot();
+    let leaf = *s.spine(true).last().unwrap();
+    s.node(leaf).4 = Some(s.free[0]);
+    refused(&s,"free or shared slot");
+}
+
+#[test]
+fn free_list_disagrees() {
+    let mut s = snapshot();
+    s.free.pop();
+    refused(&s,"free list misses a slot");
+
+    let mut s = snapshot();
+    s.free.push(s.free[0]);
+    refused(&s,"free list names a used slot");
+
+    let mut s = snapshot();
+    s.free[0] = s.root.unwrap();
+    refused(&s,"free list names a used slot");
+
+    let mut s = snapshot();
+    s.generations.pop();
+    refused(&s,"generations don't match");
+}
+
+#[test]
+fn unreachable_slot() {
+    let mut s = snapshot();
+    let root = s.root.unwrap();
+    s.node(root).3 = None;
+    refused(&s,"unreachable");
+}
@@ -0,0 +1,165 @@
+// Reference model of a `Treap`: a `BTreeMap` of values plus a map of priorities. Random
+// operation sequences are replayed against both, comparing every result and checking the
+// shape of the treap after each step. Shared by `tests/differential.rs` and the fuzz target.
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+use treap::{Treap,HeapPolicy,Subtree};
+
+// small domains, so keys collide and priorities tie
+const KEYS: u8 = 32;
+const PRIORITIES: u8 = 16;
+
+#[derive(Debug,Clone,Copy)]
+pub enum Op {
+    Insert(u8,u8,u32),
+    Remove(u8),
+    Get(u8),
+    Priority(u8),
+    Prioritize(u8,u8),
+    Pop,
+    Peek,
+    Cut(u8),
+    NthPriority(usize),
+    NthEntryPriority(usize),
+}
+
+/// Reads operations off `data` until it runs out; every byte string is a valid sequence.
+pub fn decode(data: &[u8]) -> Vec<Op> {
+    let mut bytes = data.iter().copied();
+    let mut ops = Vec::new();
+    while let Some(tag) = bytes.next() {
+        let mut next = || bytes.next().unwrap_or(0);
+        let op = match tag % 10 {
+            0 => Op::Insert(next() % KEYS,next() % PRIORITIES,u32::from(next())),
+            1 => Op::Remove(next() % KEYS),
+            2 => Op::Get(next() % KEYS),
+            3 => Op::Priority(next() % KEYS),
+            4 => Op::Prioritize(next() % KEYS,next() % PRIORITIES),
+            5 => Op::Pop,
+            6 => Op::Peek,
+            7 => Op::Cut(next() % PRIORITIES),
+            8 => Op::NthPriority(usize::from(next() % (PRIORITIES + 2))),
+            _ => Op::NthEntryPriority(usize::from(next() % (KEYS + 2))),
+        };
+        ops.push(op);
+    }
+    ops
+}
+
+#[derive(Debug,Default)]
+pub struct Model {
+    values: BTreeMap<u8,u32>,
+    priorities: BTreeMap<u8,u8>,
+}
+
+impl Model {
+    // the entry `H` puts on top: best priority, ties to `H::tie_break`
+    fn top<H: HeapPolicy>(&self) -> Option<(u8,u8,u32)> {
+        self.priorities.iter()
+            .max_by(|a,b| H::rank((a.0,a.1),(b.0,b.1)).unwrap_or(Ordering::Equal))
+            .map(|(&k,&p)| (k,p,self.values[&k]))
+    }
+    fn remove(&mut self, key: u8) -> Option<(u8,u32)> {
+        let p = self.priorities.remove(&key)?;
+        Some((p,self.values.remove(&key)?))
+    }
+    // priorities best first, `distinct` dropping repeats
+    fn ranked<H: HeapPolicy>(&self, distinct: bool) -> Vec<u8> {
+        let mut ranked: Vec<u8> = self.priorities.values().copied().collect();
+        ranked.sort_by(|a,b| H::compare(b,a).unwrap_or(Ordering::Equal));
+        if distinct { ranked.dedup(); }
+        ranked
+    }
+}
+
+/// Replays `ops` against a fresh treap and model, panicking at the first difference.
+pub fn replay<H: HeapPolicy>(ops: &[Op]) {
+    let mut treap: Treap<u8,u8,u32,(),H> = Treap::new();
+    let mut model = Model::default();
+    for (step,&op) in ops.iter().enumerate() {
+        apply(&mut treap,&mut model,op);
+        if let Err(e) = check(&treap,&model) {
+            panic!("step {} ({:?}): {}",step,op,e);
+        }
+    }
+}
+
+fn apply<H: HeapPolicy>(treap: &mut Treap<u8,u8,u32,(),H>, model: &mut Model, op: Op) {
+    match op {
+        Op::Insert(k,p,v) => {
+            let old = model.remove(k);
+            model.values.insert(k,v);
+            model.priorities.insert(k,p);
+            assert_eq!(treap.insert(k,p,v).unwrap(),old,"{:?}",op);
+        },
+        Op::Remove(k) => assert_eq!(treap.remove(&k).unwrap(),model.remove(k),"{:?}",op),
+        Op::Get(k) => {
+            let expected = model.priorities.get(&k).zip(model.values.get(&k));
+            assert_eq!(treap.get(&k).unwrap(),expected,"{:?}",op);
+        },
+        Op::Priority(k) => assert_eq!(treap.priority(&k).unwrap(),model.priorities.get(&k),"{:?}",op),
+        Op::Prioritize(k,p) => {
+            let old = model.priorities.get_mut(&k).map(|old| std::mem::replace(old,p));
+            assert_eq!(treap.prioritize(&k,p).unwrap(),old,"{:?}",op);
+        },
+        Op::Pop => {
+            let top = model.top::<H>();
+            if let Some((k,_,_)) = top { model.remove(k); }
+            assert_eq!(treap.pop().unwrap(),top,"{:?}",op);
+        },
+        Op::Peek => {
+            let top = model.top::<H>();
+            assert_eq!(treap.peek().unwrap().map(|(&k,&p,&v)| (k,p,v)),top,"{:?}",op);
+        },
+        Op::Cut(p) => {
+            let below: Vec<u8> = model.priorities.iter().filter(|e| H::below(e.1,&p)).map(|e| *e.0).collect();
+            for &k in &below { model.remove(k); }
+            assert_eq!(treap.cut(&p).unwrap(),below.len(),"{:?}",op);
+        },
+        Op::NthPriority(n) => {
+            let expected = n.checked_sub(1).and_then(|i| model.ranked::<H>(true).get(i).copied());
+            assert_eq!(treap.nth_priority(n).unwrap().copied(),expected,"{:?}",op);
+        },
+        Op::NthEntryPriority(n) => {
+            let expected = n.checked_sub(1).and_then(|i| model.ranked::<H>(false).get(i).copied());
+            assert_eq!(treap.nth_entry_priority(n).unwrap().copied(),expected,"{:?}",op);
+        },
+    }
+}
+
+// same entries as the model, keys in search order and every parent outranking its children
+fn check<H: HeapPolicy>(treap: &Treap<u8,u8,u32,(),H>, model: &Model) -> Result<(),String> {
+    fn check_node<H: HeapPolicy>(node: &Subtree<'_,u8,u8,u32>, low: Option<u8>, high: Option<u8>) -> Result<usize,String> {
+        let (&k,&p,_) = node.root();
+        if low.is_some_and(|low| k <= low) || high.is_some_and(|high| k >= high) {
+            return Err(format!("key {} out of order",k));
+        }
+        let mut size = 1;
+        for (child,low,high) in [(node.left(),low,Some(k)),(node.right(),Some(k),high)] {
+            if let Some(child) = child.map_err(|e| format!("{:?}",e))? {
+                let (&ck,&cp,_) = child.root();
+                if H::rank((&k,&p),(&ck,&cp)) != Some(Ordering::Greater) {
+                    return Err(format!("{}:{} sits above {}:{}",k,p,ck,cp));
+                }
+                size += check_node::<H>(&child,low,high)?;
+            }
+        }
+        Ok(size)
+    }
+
+    let size = match treap.root_subtree().map_err(|e| format!("{:?}",e))? {
+        Some(root) => check_node::<H>(&root,None,None)?,
+        None => 0,
+    };
+    if size != treap.len() {
+        return Err(format!("{} nodes reachable, len {}",size,treap.len()));
+    }
+    let entries: Vec<(u8,u8,u32)> = treap.iter().map(|(&k,&p,&v)| (k,p,v)).collect();
+    let expected: Vec<(u8,u8,u32)> = model.values.iter().map(|(&k,&v)| (k,model.priorities[&k],v)).collect();
+    if entries != expected {
+        return Err(format!("entries {:?}, model {:?}",entries,expected));
+    }
+    Ok(())
+}
@@ -0,0 +1,61 @@
+// `par_meld` against `meld`: same entries, same shape, same aggregates, with keys
+// repeated across the two treaps and few enough priorities that they tie.
+#![cfg(feature = "rayon")]
+
+use treap::{Treap,HeapPolicy,MaxPolicy,MinPolicy,ReverseTies,Sum,Subtree};
+
+fn numbers(seed: u64) -> impl FnMut(u64) -> u64 {
+    let mut state = seed | 1;
+    move |modulo| {
+        state ^= state >> 12;
+        state ^= state << 25;
+        state ^= state >> 27;
+        state.wrapping_mul(0x2545_f491_4f6c_dd1d) % modulo
+    }
+}
+
+fn treap<H: HeapPolicy>(next: &mut impl FnMut(u64) -> u64, len: u64, keys: u64, priorities: u64) -> Treap<u64,u64,u64,Sum<u64>,H> {
+    (0 .. len).map(|_| (next(keys),next(priorities),next(1000))).collect()
+}
+
+// keys in preorder with the aggregate of each subtree
+fn shape(node: Option<Subtree<'_,u64,u64,u64,Sum<u64>>>, out: &mut Vec<(u64,u64,u64)>) {
+    if let Some(node) = node {
+        let (&k,&p,_) = node.root();
+        out.push((k,p,node.aggregate().0));
+        shape(node.left().unwrap(),out);
+        shape(node.right().unwrap(),out);
+    }
+}
+
+fn compare<H: HeapPolicy + Send>(seed: u64) {
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(4).build().unwrap();
+    let mut next = numbers(seed);
+    let (len_a,len_b) = (next(600),next(600));
+    let (keys,priorities) = (next(800) + 1,next(8) + 1);
+    let a = treap::<H>(&mut next,len_a,keys,priorities);
+    let b = treap::<H>(&mut next,len_b,keys,priorities);
+
+    let melded = a.clone().meld(b.clone()).unwrap();
+    let par_melded = pool.install(|| a.par_meld(b)).unwrap();
+    let (mut expected,mut found) = (Vec::new(),Vec::new());
+    shape(melded.root_subtree().unwrap(),&mut expected);
+    shape(par_melded.root_subtree().unwrap(),&mut found);
+    assert_eq!(found,expected,"seed {}",seed);
+    assert_eq!(par_melded.len(),melded.len(),"seed {}",seed);
+}
+
+#[test]
+fn max_policy() {
+    for seed in 0 .. 100 { compare::<MaxPolicy>(seed); }
+}
+
+#[test]
+fn min_policy() {
+    for seed in 0 .. 100 { compare::<MinPolicy>(seed); }
+}
+
+#[test]
+fn reverse_ties() {
+    for seed in 0 .. 100 { compare::<ReverseTies<MaxPolicy>>(seed); }
+}
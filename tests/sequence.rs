@@ -0,0 +1,71 @@
+// `ImplicitTreap` against a `Vec` under random edits, splits and concatenations.
+
+use rand::rngs::StdRng;
+use treap::ImplicitTreap;
+
+// xorshift64*, as in `differential.rs`
+fn numbers(seed: u64) -> impl FnMut(usize) -> usize {
+    let mut state = seed | 1;
+    move |modulo| {
+        state ^= state >> 12;
+        state ^= state << 25;
+        state ^= state >> 27;
+        (state.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 1) as usize % modulo.max(1)
+    }
+}
+
+fn check(seq: &ImplicitTreap<u32>, model: &[u32], step: usize) {
+    assert_eq!(seq.len(),model.len(),"step {}",step);
+    assert!(seq.iter().copied().eq(model.iter().copied()),"step {}",step);
+}
+
+#[test]
+fn against_vec() {
+    for seed in 0 .. 100 {
+        let mut next = numbers(seed);
+        let mut seq: ImplicitTreap<u32,StdRng> = ImplicitTreap::with_seed(seed);
+        let mut model: Vec<u32> = Vec::new();
+        for step in 0 .. 400 {
+            let len = model.len();
+            let (a,b) = (next(len + 1),next(len + 1));
+            let (a,b) = (a.min(b),a.max(b));
+            match next(9) {
+                0 | 1 => {
+                    let v = next(1000) as u32;
+                    seq.insert_at(a,v).unwrap();
+                    model.insert(a,v);
+                },
+                2 => assert_eq!(seq.remove_at(a).unwrap(),(a < len).then(|| model.remove(a)),"step {}",step),
+                3 => {
+                    seq.reverse_range(a .. b).unwrap();
+                    model[a .. b].reverse();
+                },
+                4 => {
+                    let dest = next(len - (b - a) + 1);
+                    seq.move_range(a .. b,dest).unwrap();
+                    let moved: Vec<u32> = model.drain(a .. b).collect();
+                    model.splice(dest .. dest,moved);
+                },
+                5 => {
+                    seq.rotate_left(a).unwrap();
+                    model.rotate_left(a);
+                },
+                6 => {
+                    let (l,r) = seq.split_at(a).unwrap();
+                    check(&l,&model[.. a],step);
+                    check(&r,&model[a ..],step);
+                    seq = l.concat(r).unwrap();
+                },
+                7 => {
+                    if let Some(v) = seq.get_mut(a).unwrap() { *v += 1; }
+                    if let Some(v) = model.get_mut(a) { *v += 1; }
+                },
+                _ => {
+                    assert!(seq.slice(a .. b).unwrap().copied().eq(model[a .. b].iter().copied()),"step {}",step);
+                    assert_eq!(seq.get(a).unwrap(),model.get(a),"step {}",step);
+                },
+            }
+            check(&seq,&model,step);
+        }
+    }
+}
@@ -0,0 +1,53 @@
+// Access stats have to outlive the O(n) rebuilds, which move every node to a new arena.
+#![cfg(feature = "access-stats")]
+
+use treap::{Treap,AccessStats};
+
+type T = Treap<u32,u32,u32>;
+
+fn treap() -> T {
+    (0 .. 100u32).map(|i| (i,i.wrapping_mul(2654435761) >> 8,i)).collect()
+}
+
+// reads 10, twice, and 20 once
+fn read(t: &T) -> (AccessStats,AccessStats) {
+    t.get(&10).unwrap();
+    t.get(&20).unwrap();
+    t.get(&10).unwrap();
+    (t.access_stats(&10).unwrap().unwrap(),t.access_stats(&20).unwrap().unwrap())
+}
+
+fn kept(t: &T, stats: (AccessStats,AccessStats)) {
+    assert_eq!((t.access_stats(&10).unwrap().unwrap(),t.access_stats(&20).unwrap().unwrap()),stats);
+    assert_eq!(t.access_stats(&30).unwrap().unwrap(),AccessStats::default());
+}
+
+#[test]
+fn scale_priorities() {
+    let mut t = treap();
+    let stats = read(&t);
+    assert_eq!(stats.0.count,2);
+    t.scale_priorities(|p| u32::MAX - p).unwrap();
+    kept(&t,stats);
+    let coldest: Vec<u32> = t.coldest().map(|(k,_,_,_)| *k).collect();
+    assert_eq!(&coldest[98 ..],&[20,10]);
+}
+
+#[test]
+fn other_rebuilds() {
+    let mut t = treap();
+    let stats = read(&t);
+    t.map_priorities(|k,p| p ^ k).unwrap();
+    kept(&t,stats);
+    t.bulk_prioritize((0 .. 50).map(|k| (k,k))).unwrap();
+    kept(&t,stats);
+    t.retain_mut(|k,_,_| k % 10 == 0 || *k == 30).unwrap();
+    kept(&t,stats);
+    t.retain_keys(&treap()).unwrap();
+    kept(&t,stats);
+    let (t,_) = t.partition(|k,_,_| *k < 50).unwrap();
+    kept(&t,stats);
+    let (hot,cold) = t.split_by_priority(&15).unwrap();
+    assert_eq!(hot.access_stats(&20).unwrap().unwrap(),stats.1);
+    assert_eq!(cold.access_stats(&10).unwrap().unwrap(),stats.0);
+}